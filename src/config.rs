@@ -0,0 +1,63 @@
+use std::error::Error;
+use serde::Deserialize;
+
+
+// Settings read from the JSON file given with --config. Flags given on the command line take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub http: HttpConfig,
+}
+
+// Outbound HTTP settings, such as for a corporate egress proxy with a TLS intercepting CA
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    // Proxy for every request, such as http://proxy:3128 or socks5://proxy:1080
+    pub proxy: Option<String>,
+    // Proxies for plain and TLS requests only, used instead of the proxy for every request
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    // Comma separated hosts, domains and IP ranges reached without the proxy
+    pub no_proxy: Option<String>,
+    // PEM files of extra root certificates, each of which may hold a bundle
+    pub ca_files: Vec<String>,
+    pub user_agent: Option<String>,
+}
+
+pub static DEFAULT_USER_AGENT: &str = "BlossomiShymae/smol-profile-card";
+
+impl Config {
+    pub fn load(path: &str) -> Result<Config, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+impl HttpConfig {
+    // Settings of the command line over the ones of the file, extra root certificates are added up
+    pub fn merge(self, other: HttpConfig) -> HttpConfig {
+        HttpConfig {
+            proxy: other.proxy.or(self.proxy),
+            http_proxy: other.http_proxy.or(self.http_proxy),
+            https_proxy: other.https_proxy.or(self.https_proxy),
+            no_proxy: other.no_proxy.or(self.no_proxy),
+            ca_files: self.ca_files.into_iter().chain(other.ca_files).collect(),
+            user_agent: other.user_agent.or(self.user_agent),
+        }
+    }
+
+    pub fn get_user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+    }
+}
+
+// Split a PEM bundle into its certificates
+pub fn split_pem_bundle(pem: &str) -> Vec<String> {
+    let end_marker = "-----END CERTIFICATE-----";
+    pem.split_inclusive(end_marker)
+        .filter_map(|part| part.find("-----BEGIN CERTIFICATE-----").map(|start| &part[start..]))
+        .filter(|part| part.ends_with(end_marker))
+        .map(|part| part.to_string())
+        .collect()
+}
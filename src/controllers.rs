@@ -1,11 +1,19 @@
+pub mod admin;
+pub mod health;
 pub mod index;
 pub mod image;
+pub mod local;
 
+use axum::Json;
 use axum::response::{Html, Response, IntoResponse};
 use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, RETRY_AFTER};
 use serde::{Serialize};
 use handlebars::Handlebars;
 
+use crate::errors::AppError;
+use crate::time;
+
 
 #[derive(Debug, Serialize)]
 pub struct TemplateViewModel {
@@ -23,7 +31,7 @@ pub async fn get_error_page(registry: &Handlebars<'static>, status_code: StatusC
     let status_title = get_status_title(status_code);
     let template_error_vm = TemplateErrorViewModel {
         code: status_code.as_u16(),
-        reason: status_code.canonical_reason().unwrap_or_else(|| {""}).to_string()
+        reason: status_code.canonical_reason().unwrap_or("").to_string()
     };
     let error_r = registry.render("errors/template", &template_error_vm).unwrap();
 
@@ -36,8 +44,83 @@ pub async fn get_error_page(registry: &Handlebars<'static>, status_code: StatusC
     (status_code, Html(r)).into_response()
 }
 
+#[derive(Debug, Serialize)]
+pub struct ErrorViewModel {
+    pub error: String,
+}
+
+// Error response of the JSON APIs
+pub fn json_error(status_code: StatusCode, message: &str) -> Response {
+    (status_code, Json(ErrorViewModel { error: message.to_string() })).into_response()
+}
+
+pub async fn get_retry_error_page(registry: &Handlebars<'static>, status_code: StatusCode, retry_after: i64) -> Response {
+    let mut response = get_error_page(registry, status_code).await;
+    if let Ok(value) = HeaderValue::from_str(&retry_after.max(0).to_string()) {
+        response.headers_mut().insert(RETRY_AFTER, value);
+    }
+    response
+}
+
+// Error page of a failed fetch, rate limits tell the client when the provider resets
+pub async fn get_app_error_page(registry: &Handlebars<'static>, error: &AppError) -> Response {
+    let status_code = get_app_error_status(error);
+    if let AppError::RateLimited { reset } | AppError::Unavailable { reset } = error {
+        log::warn!("{}", error);
+        return get_retry_error_page(registry, status_code, reset - time::get_timestamp()).await;
+    }
+    log_app_error(status_code, error);
+    get_error_page(registry, status_code).await
+}
+
+// Error response of the JSON APIs for a failed fetch
+pub fn json_app_error(error: &AppError) -> Response {
+    let status_code = get_app_error_status(error);
+    log_app_error(status_code, error);
+    json_error(status_code, &error.to_string())
+}
+
+fn get_app_error_status(error: &AppError) -> StatusCode {
+    match error {
+        AppError::NotFound(_) => StatusCode::NOT_FOUND,
+        AppError::Invalid(_) => StatusCode::BAD_REQUEST,
+        AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+        AppError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        AppError::Decode(_) | AppError::Storage(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn log_app_error(status_code: StatusCode, error: &AppError) {
+    if status_code.is_server_error() {
+        log::error!("{}", error);
+    } else {
+        log::info!("{}", error);
+    }
+}
+
 fn get_status_title(status_code: StatusCode) -> String {
     let code = status_code.as_u16().to_string();
-    let reason = status_code.canonical_reason().unwrap_or_else(|| {""}).to_string();
+    let reason = status_code.canonical_reason().unwrap_or("").to_string();
     [code, reason].join(" ")
+}
+
+// Check the bearer token of a request against the admin token. Without a configured token nothing is authorized.
+pub fn is_authorized(headers: &HeaderMap, admin_token: &Option<String>) -> bool {
+    let token = match admin_token {
+        Some(token) => token,
+        None => return false
+    };
+    let given = headers.get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or("");
+    is_token_equal(given, token)
+}
+
+// Compare tokens in constant time so the token can't be guessed from response timings.
+pub fn is_token_equal(given: &str, token: &str) -> bool {
+    given.len() == token.len() && given.bytes()
+        .zip(token.bytes())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
 }
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+use super::image::is_provider_username_valid;
+
+
+#[derive(Debug, Serialize)]
+pub struct PurgeViewModel {
+    purged: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsersQueryViewModel {
+    provider: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurgeUsersQueryViewModel {
+    // Only purge the users past their expiration
+    expired: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VacuumViewModel {
+    vacuumed: bool,
+}
+
+pub async fn get_refresh_status(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    Json(state.refresh_service.get_status()).into_response()
+}
+
+pub async fn get_avatar_cache(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    match state.github_user_service.avatar_cache.get_stats().await {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn delete_avatar_cache(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    match state.github_user_service.avatar_cache.purge().await {
+        Ok(purged) => Json(PurgeViewModel { purged }).into_response(),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn get_users(query: Query<UsersQueryViewModel>, headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    match state.github_user_service.get_cached_users(query.0.provider.as_deref()).await {
+        Ok(users) => Json(users).into_response(),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn delete_users(query: Query<PurgeUsersQueryViewModel>, headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    match state.github_user_service.purge_users(query.0.expired.unwrap_or(false)).await {
+        Ok(purged) => Json(PurgeViewModel { purged }).into_response(),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn get_user(Path((provider, username)): Path<(String, String)>, headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if let Some(response) = check_user_request(&state, &headers, &provider, &username) {
+        return response;
+    }
+
+    match state.github_user_service.get_cached_user(&provider, &username).await {
+        Ok(Some(user)) => Json(user).into_response(),
+        Ok(None) => super::json_error(StatusCode::NOT_FOUND, "Cached user not found"),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn post_user_refresh(Path((provider, username)): Path<(String, String)>, headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if let Some(response) = check_user_request(&state, &headers, &provider, &username) {
+        return response;
+    }
+
+    match state.github_user_service.force_refresh(&provider, &username).await {
+        Ok(Some(user)) => Json(user).into_response(),
+        Ok(None) => super::json_error(StatusCode::NOT_FOUND, "User not found"),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn delete_user(Path((provider, username)): Path<(String, String)>, headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if let Some(response) = check_user_request(&state, &headers, &provider, &username) {
+        return response;
+    }
+
+    match state.github_user_service.purge_user(&provider, &username).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => super::json_error(StatusCode::NOT_FOUND, "Cached user not found"),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn post_vacuum(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    match state.github_user_service.vacuum().await {
+        Ok(()) => Json(VacuumViewModel { vacuumed: true }).into_response(),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+// Check the token, provider and username of a request about a single user
+fn check_user_request(state: &AppState, headers: &HeaderMap, provider: &str, username: &str) -> Option<Response> {
+    if !super::is_authorized(headers, &state.admin_token) {
+        return Some(super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized"));
+    }
+    if !state.github_user_service.has_provider(provider) {
+        return Some(super::json_error(StatusCode::BAD_REQUEST, "Unknown provider"));
+    }
+    if !is_provider_username_valid(Some(provider), username) {
+        return Some(super::json_error(StatusCode::BAD_REQUEST, "Invalid username"));
+    }
+    None
+}
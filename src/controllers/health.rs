@@ -0,0 +1,19 @@
+use std::sync::Arc;
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::AppState;
+use crate::models::health::Health;
+
+
+// Health of the server and the upstream providers, open to load balancers and monitoring
+pub async fn get_health(State(state): State<Arc<AppState>>) -> Response {
+    let github = state.github_api_service.get_circuit_status();
+    let status = if github.state == "closed" { "ok" } else { "degraded" };
+
+    Json(Health {
+        status: status.to_string(),
+        github,
+    }).into_response()
+}
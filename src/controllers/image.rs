@@ -1,39 +1,163 @@
-use std::io::{BufWriter, Cursor};
 use std::sync::Arc;
 use axum::body::Full;
-use axum::http::{StatusCode};
+use axum::http::{HeaderValue, StatusCode};
 use axum::extract::{State, Query};
 use axum::response::{Response, IntoResponse, Html};
-use image::imageops::FilterType;
-use image::{Rgba, ImageFormat, DynamicImage, RgbaImage, ImageBuffer};
 use serde::Deserialize;
-use rusttype::{Scale, Font};
+use tokio::task::JoinSet;
 
 use crate::AppState;
+use crate::errors::AppError;
+use crate::mappers::local_profile_mapper;
+use crate::models::profile::Profile;
+use crate::providers::fediverse_provider::FediverseProvider;
+use crate::renderers::{card_renderer, org_renderer, repo_renderer, team_renderer};
+use crate::renderers::team_renderer::{TeamLayout, TeamMember};
+use crate::renderers::theme::Theme;
+use crate::services::avatar_service::AvatarSource;
+use crate::services::github_user_service::DEFAULT_PROVIDER;
+use crate::services::render_service::RenderError;
 use crate::validators;
 
 
+// Seconds a client should wait before retrying when the render queue is full
+const RENDER_RETRY_AFTER: i64 = 1;
+// Maximum number of users in a team roster
+const TEAM_MAX_USERS: usize = 24;
+const TEAM_MAX_COLUMNS: u32 = 8;
+const TEAM_MAX_GAP: u32 = 64;
+
 #[derive(Debug, Deserialize)]
 pub struct GithubUserViewModel {
-    user: String,
-    pronouns: Option<String>
+    user: Option<String>,
+    pronouns: Option<String>,
+    theme: Option<Theme>,
+    provider: Option<String>,
+    // Slug of a locally managed profile, used instead of user
+    local: Option<String>,
+    avatar_source: Option<AvatarSource>,
+    // Email hashed to look up the Gravatar or Libravatar avatar
+    email: Option<String>,
 }
 
 impl GithubUserViewModel {
     pub fn is_valid(&self) -> bool {
-        // Github username has a 39 character limit
-        let mut is_user_valid = validators::is_str_valid_length(&self.user, 0, 39);
-        is_user_valid = is_user_valid && validators::is_str_delimiter_free(&self.user);
+        let is_user_valid = match (&self.user, &self.local) {
+            (Some(user), None) => is_provider_username_valid(self.provider.as_deref(), user),
+            (None, Some(slug)) => super::local::is_slug_valid(slug),
+            _ => false
+        };
 
         let is_pronouns_valid = match &self.pronouns {
-            Some(pronouns) => validators::is_str_delimiter_free(&pronouns),
+            Some(pronouns) => validators::is_str_delimiter_free(pronouns),
+            None => true
+        };
+
+        is_user_valid && is_pronouns_valid && is_avatar_source_valid(self.avatar_source, self.email.as_deref())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TeamViewModel {
+    users: String,
+    columns: Option<u32>,
+    gap: Option<u32>,
+    title: Option<String>,
+    theme: Option<Theme>,
+    provider: Option<String>,
+    avatar_source: Option<AvatarSource>,
+}
+
+impl TeamViewModel {
+    pub fn usernames(&self) -> Vec<String> {
+        self.users.split(',')
+            .map(normalize_username)
+            .filter(|user| !user.is_empty())
+            .collect()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        let usernames = self.usernames();
+        let is_users_valid = !usernames.is_empty()
+            && usernames.len() <= TEAM_MAX_USERS
+            && usernames.iter().all(|user| is_provider_username_valid(self.provider.as_deref(), user));
+
+        let is_columns_valid = self.columns.is_none_or(|columns| (1..=TEAM_MAX_COLUMNS).contains(&columns));
+        let is_gap_valid = self.gap.is_none_or(|gap| gap <= TEAM_MAX_GAP);
+        let is_title_valid = match &self.title {
+            Some(title) => validators::is_str_valid_length(title, 1, 64),
             None => true
         };
 
-        is_user_valid && is_pronouns_valid
+        // Members have no email to look up, so only sources without one are allowed
+        let is_avatar_source_valid = is_avatar_source_valid(self.avatar_source, None);
+
+        is_users_valid && is_columns_valid && is_gap_valid && is_title_valid && is_avatar_source_valid
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GithubOrgViewModel {
+    org: String,
+    theme: Option<Theme>,
+}
+
+impl GithubOrgViewModel {
+    pub fn is_valid(&self) -> bool {
+        // Organisation logins share the username rules
+        is_username_valid(&self.org)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GithubRepoViewModel {
+    owner: String,
+    repo: String,
+    theme: Option<Theme>,
+}
+
+impl GithubRepoViewModel {
+    pub fn is_valid(&self) -> bool {
+        // Github repository names have a 100 character limit
+        let is_repo_valid = validators::is_str_valid_length(&self.repo, 1, 100)
+            && validators::is_str_delimiter_free(&self.repo);
+
+        is_username_valid(&self.owner) && is_repo_valid
+    }
+}
+
+fn is_username_valid(user: &str) -> bool {
+    // Github username has a 39 character limit
+    validators::is_str_valid_length(user, 0, 39) && validators::is_str_delimiter_free(user)
+}
+
+pub fn is_provider_username_valid(provider: Option<&str>, user: &str) -> bool {
+    match provider {
+        None => is_username_valid(user),
+        Some(provider) if provider == DEFAULT_PROVIDER => is_username_valid(user),
+        // Fediverse handles are in the form of @user@instance
+        Some("fediverse") => validators::is_str_valid_length(user, 3, 255) && FediverseProvider::parse_handle(user).is_some(),
+        // Other forges allow longer usernames
+        Some(_) => validators::is_str_valid_length(user, 1, 255) && validators::is_str_delimiter_free(user)
+    }
+}
+
+// Fediverse handles may be given as @user@instance but are cached as user@instance, so the leading @ is dropped
+fn normalize_username(user: &str) -> String {
+    user.trim().trim_start_matches('@').to_string()
+}
+
+fn is_avatar_source_valid(avatar_source: Option<AvatarSource>, email: Option<&str>) -> bool {
+    let is_email_valid = match email {
+        // Emails have a 254 character limit
+        Some(email) => validators::is_str_valid_length(email, 3, 254) && email.contains('@'),
+        None => true
+    };
+    let is_email_required = avatar_source.is_some_and(|avatar_source| avatar_source.requires_email());
+
+    is_email_valid && (!is_email_required || email.is_some())
+}
+
 #[axum_macros::debug_handler]
 pub async fn get_index(query: Query<GithubUserViewModel>, State(state): State<Arc<AppState>>) -> Response {
     let vm = query.0;
@@ -42,7 +166,7 @@ pub async fn get_index(query: Query<GithubUserViewModel>, State(state): State<Ar
             .await;
     }
 
-    let username = vm.user.to_string();
+    let theme = vm.theme.unwrap_or_default();
     let pronouns = vm.pronouns;
     let pronouns_tag = match pronouns {
         Some(query) => state.pronouns_mapper
@@ -51,142 +175,286 @@ pub async fn get_index(query: Query<GithubUserViewModel>, State(state): State<Ar
         None => String::from("")
     };
 
+    let avatar_source = vm.avatar_source.unwrap_or_default();
+    let email = vm.email;
+    if let Some(slug) = vm.local {
+        return get_local_card(&state, &slug, pronouns_tag, theme, avatar_source, email.as_deref()).await;
+    }
+
+    let username = normalize_username(&vm.user.unwrap_or_default());
+    let provider = vm.provider.unwrap_or(DEFAULT_PROVIDER.to_string());
+    if !state.github_user_service.has_provider(&provider) {
+        return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await;
+    }
+
+    log::trace!("Provider: {}", provider);
     log::trace!("User: {}", username);
+    state.refresh_service.record(&provider, &username);
     log::trace!("Pronouns: {}", pronouns_tag);
 
     let user_result = state.github_user_service
-        .get_by_username(&username)
+        .get_cached_by_username(&provider, &username)
         .await;
 
-    if let Ok(user_option) = user_result {
-        if let Some(user) = user_option {
-            let avatar_result = state.github_user_service.get_avatar_by_id(user.id).await;
-            if avatar_result.is_err() {
-                return super::get_error_page(&state.registry, StatusCode::INTERNAL_SERVER_ERROR).await
-            }
+    let cached_user = match user_result {
+        Ok(Some(cached_user)) => cached_user,
+        Ok(None) => return not_found_response(&state, &username, theme).await,
+        Err(e) => return super::get_app_error_page(&state.registry, &e).await
+    };
+    let user = cached_user.value;
+    let avatar_result = state.github_user_service
+        .get_avatar_from(&user, avatar_source, email.as_deref())
+        .await;
+    let avatar = match avatar_result {
+        Ok(avatar) => avatar,
+        Err(e) => return super::get_app_error_page(&state.registry, &e).await
+    };
 
-            // Load avatar image
-            let avatar = avatar_result.unwrap();
-            let mut avatar_img = image::load_from_memory(&avatar).unwrap();
-            // Border round the avatar
-            let mut canvas_avatar = avatar_img.to_rgba8();
-            round_image_mut(&mut canvas_avatar);
-            // Overlay avatar onto image
-            avatar_img = canvas_avatar.into();
-            avatar_img = avatar_img.resize(100, 100, FilterType::Lanczos3);
-            let mut card_img = draw_image(&user, &pronouns_tag).await;
-            image::imageops::overlay(&mut card_img, &avatar_img, 20, 10);
-            // Serialize image
-            let mut buffer = BufWriter::new(Cursor::new(Vec::new()));
-            card_img.write_to(&mut buffer, ImageFormat::Png).unwrap();
-            let bytes: Vec<u8> = buffer.into_inner().unwrap().into_inner();
-            
-            return Response::builder()
-                .status(StatusCode::OK)
-                .header("Content-Type", "image/png")
-                .header("Content-Length", bytes.len().to_string())
-                .body(Full::from(bytes))
-                .unwrap()
-                .into_response();
-        }
+    let pronouns_tag = with_profile_pronouns(pronouns_tag, &user);
+    let render_result = state.render_service
+        .render(move || card_renderer::render_card(&user, &pronouns_tag, &avatar, theme))
+        .await;
+
+    with_stale_headers(render_response(&state, render_result).await, cached_user.stale_for)
+}
+
+// Mark a response rendered from a cached profile that is past its expiration
+fn with_stale_headers(mut response: Response, stale_for: Option<i64>) -> Response {
+    if let Some(stale_for) = stale_for.filter(|_| response.status().is_success()) {
+        let headers = response.headers_mut();
+        headers.insert("Warning", HeaderValue::from_static("110 smol-profile-card \"Response is Stale\""));
+        headers.insert("X-Stale-For", HeaderValue::from(stale_for));
     }
+    response
+}
 
-    super::get_error_page(&state.registry, StatusCode::INTERNAL_SERVER_ERROR).await
+// Pronouns from the query take precedence over the ones of the profile
+fn with_profile_pronouns(pronouns_tag: String, user: &Profile) -> String {
+    if pronouns_tag.is_empty() {
+        user.pronouns.clone().unwrap_or_default()
+    } else {
+        pronouns_tag
+    }
 }
 
-pub async fn get_html(query: Query<GithubUserViewModel>, State(state): State<Arc<AppState>>) -> Response {
+async fn get_local_card(state: &AppState, slug: &str, pronouns_tag: String, theme: Theme, avatar_source: AvatarSource, email: Option<&str>) -> Response {
+    log::trace!("Local: {}", slug);
+
+    let profile = match state.local_profile_service.get_by_slug(slug).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return super::get_error_page(&state.registry, StatusCode::NOT_FOUND).await,
+        Err(e) => return super::get_app_error_page(&state.registry, &e).await
+    };
+    let user = local_profile_mapper::to_profile(&profile);
+    // Local profiles have no provider to fetch from, so the stored avatar is the fallback
+    let avatar = match state.github_user_service.avatars.get_avatar(avatar_source, &user, email).await {
+        Ok(Some(avatar)) => avatar,
+        Ok(None) => match &profile.avatar {
+            Some(avatar) => avatar.clone(),
+            None => return super::get_error_page(&state.registry, StatusCode::INTERNAL_SERVER_ERROR).await
+        },
+        Err(e) => return super::get_app_error_page(&state.registry, &e).await
+    };
+    let pronouns_tag = with_profile_pronouns(pronouns_tag, &user);
+    let render_result = state.render_service
+        .render(move || card_renderer::render_card(&user, &pronouns_tag, &avatar, theme))
+        .await;
+
+    render_response(state, render_result).await
+}
+
+pub async fn get_team(query: Query<TeamViewModel>, State(state): State<Arc<AppState>>) -> Response {
     let vm = query.0;
     if !vm.is_valid() {
         return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await;
     }
 
+    let usernames = vm.usernames();
+    let provider = vm.provider.clone().unwrap_or(DEFAULT_PROVIDER.to_string());
+    if !state.github_user_service.has_provider(&provider) {
+        return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await;
+    }
+    let avatar_source = vm.avatar_source.unwrap_or_default();
+    log::trace!("Team: {}", usernames.join(","));
+
+    // Fetch every profile and avatar concurrently
+    let mut tasks = JoinSet::new();
+    for (index, username) in usernames.iter().enumerate() {
+        let state = state.clone();
+        let username = username.clone();
+        let provider = provider.clone();
+        state.refresh_service.record(&provider, &username);
+        tasks.spawn(async move {
+            let result = async {
+                let cached_user = match state.github_user_service.get_cached_by_username(&provider, &username).await? {
+                    Some(cached_user) => cached_user,
+                    None => return Ok(None)
+                };
+                let avatar = state.github_user_service.get_avatar_from(&cached_user.value, avatar_source, None).await?;
+                Ok::<_, AppError>(Some((cached_user.value, avatar, cached_user.stale_for)))
+            }.await;
+            (index, username, result)
+        });
+    }
+
+    // Missing or failed members are drawn as placeholders so the rest of the roster still renders
+    let mut members = Vec::with_capacity(usernames.len());
+    let mut stale_for = None;
+    while let Some(result) = tasks.join_next().await {
+        let (index, username, result) = match result {
+            Ok(result) => result,
+            Err(_) => {
+                tasks.abort_all();
+                return super::get_error_page(&state.registry, StatusCode::INTERNAL_SERVER_ERROR).await;
+            }
+        };
+        let member = match result {
+            Ok(Some((user, avatar, member_stale_for))) => {
+                // The roster is as stale as its stalest member
+                stale_for = stale_for.max(member_stale_for);
+                TeamMember::Found(Box::new(user), avatar)
+            },
+            Ok(None) => TeamMember::Missing(username),
+            Err(e) => {
+                log::warn!("Failed to fetch team member {}: {}", username, e);
+                TeamMember::Failed(username)
+            }
+        };
+        members.push((index, member));
+    }
+    // Keep the order the users were requested in
+    members.sort_by_key(|(index, _)| *index);
+    let members: Vec<_> = members.into_iter()
+        .map(|(_, member)| member)
+        .collect();
+
+    let layout = TeamLayout {
+        columns: vm.columns.unwrap_or(2),
+        gap: vm.gap.unwrap_or(10),
+        title: vm.title,
+        theme: vm.theme.unwrap_or_default(),
+    };
+    let render_result = state.render_service
+        .render(move || team_renderer::render_team(&members, &layout))
+        .await;
+
+    with_stale_headers(render_response(&state, render_result).await, stale_for)
+}
+
+pub async fn get_html(query: Query<GithubUserViewModel>, State(state): State<Arc<AppState>>) -> Response {
+    let vm = query.0;
+    let user = match &vm.user {
+        Some(user) if vm.is_valid() => user.clone(),
+        _ => return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await
+    };
+
     let r = format!("<img class=\"img-fluid img-thumbnail\" src=\"http://localhost:8080/image?user={}&pronouns={}\"/>",
-        vm.user.as_str(),
+        user.as_str(),
         vm.pronouns.unwrap_or("".to_string()).as_str()
     );
     log::debug!("{}", r);
-    return (StatusCode::OK, Html(r)).into_response();
-}
-
-fn round_image_mut(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>) {
-    let dim = image.dimensions();
-    let mut canvas_mask = RgbaImage::new(dim.0, dim.1);
-    let width = dim.0 as f32;
-    let height = dim.1 as f32;
-    let midpoint = ((width/2.0) as i32, (height/2.0) as i32);
-    let radius = (width as i32) - midpoint.0;
-    imageproc::drawing::draw_filled_circle_mut(
-        &mut canvas_mask, 
-        midpoint,
-        radius,
-        Rgba([255,255,255,255])
-    );
-    // Apply clip mask
-    for (avatar_p, mask_p) in image.pixels_mut().zip(canvas_mask.pixels_mut()) {
-        if mask_p.0 != [255,255,255,255] {
-            avatar_p.0 = [0,0,0,0];
-        }
+    (StatusCode::OK, Html(r)).into_response()
+}
+
+pub async fn get_org(query: Query<GithubOrgViewModel>, State(state): State<Arc<AppState>>) -> Response {
+    let vm = query.0;
+    if !vm.is_valid() {
+        return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await;
+    }
+
+    let theme = vm.theme.unwrap_or_default();
+    log::trace!("Org: {}", vm.org);
+
+    let org_result = state.github_org_service
+        .get_by_login(&vm.org)
+        .await;
+
+    let org = match org_result {
+        Ok(Some(org)) => org,
+        Ok(None) => return super::get_error_page(&state.registry, StatusCode::NOT_FOUND).await,
+        Err(e) => return super::get_app_error_page(&state.registry, &e).await
+    };
+    // Organisation logos are served the same way as user avatars
+    let avatar = match state.github_user_service.get_avatar_by_id(i64::from(org.id)).await {
+        Ok(avatar) => avatar,
+        Err(e) => return super::get_app_error_page(&state.registry, &e).await
+    };
+
+    let render_result = state.render_service
+        .render(move || org_renderer::render_org(&org, &avatar, theme))
+        .await;
+
+    render_response(&state, render_result).await
+}
+
+pub async fn get_repo(query: Query<GithubRepoViewModel>, State(state): State<Arc<AppState>>) -> Response {
+    let vm = query.0;
+    if !vm.is_valid() {
+        return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await;
     }
+
+    let theme = vm.theme.unwrap_or_default();
+    log::trace!("Repo: {}/{}", vm.owner, vm.repo);
+
+    let repo_result = state.github_repo_service
+        .get_by_name(&vm.owner, &vm.repo)
+        .await;
+
+    let repo = match repo_result {
+        Ok(Some(repo)) => repo,
+        Ok(None) => return super::get_error_page(&state.registry, StatusCode::NOT_FOUND).await,
+        Err(e) => return super::get_app_error_page(&state.registry, &e).await
+    };
+    let avatar = match state.github_user_service.get_avatar_by_id(i64::from(repo.owner.id)).await {
+        Ok(avatar) => avatar,
+        Err(e) => return super::get_app_error_page(&state.registry, &e).await
+    };
+
+    let language_color = repo.language.as_ref()
+        .map(|language| state.language_color_mapper.to_color(language))
+        .unwrap_or_default();
+    let render_result = state.render_service
+        .render(move || repo_renderer::render_repo(&repo, &avatar, language_color, theme))
+        .await;
+
+    render_response(&state, render_result).await
 }
 
-async fn draw_image(user: &crate::models::github_user::GithubUser, pronouns_tag: &str) -> DynamicImage {
-    // Create profile card image
-    let mut img = image::open("images/dark_template.png").unwrap();
-    let mut location_img = image::open("images/location.png").unwrap();
-    let regular_font_data = Vec::from(include_bytes!("../../fonts/Oxygen-Regular.ttf") as &[u8]);
-    let regular_font = Font::try_from_vec(regular_font_data).unwrap();
-    let light_font_data = Vec::from(include_bytes!("../../fonts/Oxygen-Light.ttf") as &[u8]);
-    let light_font = Font::try_from_vec(light_font_data).unwrap();
-    let big_font_size = 24.0;
-    let smol_font_size = 20.0;
-    
-    // Draw the person's name
-    {
-        let left_margin = 140;
-        let user = user.clone();
-        imageproc::drawing::draw_text_mut(
-            &mut img, 
-            Rgba([255u8, 255u8, 255u8, 255u8]), 
-            left_margin, 
-            20, 
-            Scale { x: big_font_size, y: big_font_size },
-            &regular_font, 
-            &user.name.unwrap_or(user.login.to_string())
-        );
-        // Draw the person's location
-        let location = &user.location.unwrap_or("".to_string());
-        imageproc::drawing::draw_text_mut(
-            &mut img, 
-            Rgba([192u8, 192u8, 192u8, 255u8]), 
-            left_margin + 20, 
-            50, 
-            Scale {
-                x: smol_font_size,
-                y: smol_font_size
-            }, 
-            &light_font,
-            location
-        );
-        // Overlay location icon
-        if location.ne("") {
-            image::imageops::invert(&mut location_img);
-            let buffer = image::imageops::brighten(&location_img, -25);
-            image::imageops::overlay(&mut img, &buffer, i64::from(left_margin), 55);
+async fn render_response(state: &AppState, render_result: Result<Vec<u8>, RenderError>) -> Response {
+    match render_result {
+        Ok(bytes) => png_response(bytes),
+        Err(RenderError::QueueFull) => super::get_retry_error_page(
+            &state.registry,
+            StatusCode::SERVICE_UNAVAILABLE,
+            RENDER_RETRY_AFTER
+        ).await,
+        Err(RenderError::Failed(e)) => {
+            log::error!("Failed to render card: {}", e);
+            super::get_error_page(&state.registry, StatusCode::INTERNAL_SERVER_ERROR).await
         }
-        // Draw the person's pronouns
-        imageproc::drawing::draw_text_mut(
-            &mut img, 
-            Rgba([192u8, 192u8, 192u8, 255u8]), 
-            left_margin, 
-            78, 
-            Scale {
-                x: smol_font_size,
-                y: smol_font_size
-            }, 
-            &light_font, 
-            pronouns_tag.clone()
-        );
-    };   
-
-    img
+    }
+}
+
+// Render the card of a username that does not exist, so embeds show why instead of a broken image
+async fn not_found_response(state: &AppState, username: &str, theme: Theme) -> Response {
+    let username = username.to_string();
+    let render_result = state.render_service
+        .render(move || card_renderer::render_not_found(&username, theme))
+        .await;
+
+    let mut response = render_response(state, render_result).await;
+    if response.status().is_success() {
+        *response.status_mut() = StatusCode::NOT_FOUND;
+    }
+    response
+}
+
+fn png_response(bytes: Vec<u8>) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "image/png")
+        .header("Content-Length", bytes.len().to_string())
+        .body(Full::from(bytes))
+        .unwrap()
+        .into_response()
 }
\ No newline at end of file
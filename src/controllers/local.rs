@@ -0,0 +1,268 @@
+use std::sync::Arc;
+use axum::extract::{Multipart, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+use crate::errors::AppError;
+use crate::models::local_profile::LocalProfile;
+use crate::renderers::card_renderer;
+use crate::time;
+use crate::validators;
+
+
+// Largest avatar upload accepted, before it is resized
+const MAX_AVATAR_BYTES: usize = 1024 * 1024;
+// Size avatars are stored at
+const AVATAR_SIZE: u32 = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct LocalProfileViewModel {
+    name: String,
+    pronouns: Option<String>,
+    location: Option<String>,
+    tagline: Option<String>,
+    // Base64 encoded image, keeping the stored avatar when omitted
+    avatar: Option<String>,
+}
+
+impl LocalProfileViewModel {
+    pub fn is_valid(&self) -> bool {
+        let is_optional_valid = |value: &Option<String>, max: usize| match value {
+            Some(value) => validators::is_str_valid_length(value, 0, max),
+            None => true
+        };
+
+        validators::is_str_valid_length(self.name.trim(), 1, 64)
+            && is_optional_valid(&self.pronouns, 32)
+            && is_optional_valid(&self.location, 64)
+            && is_optional_valid(&self.tagline, 80)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LocalFormQueryViewModel {
+    slug: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct LocalFormViewModel {
+    slug: String,
+    name: String,
+    pronouns: String,
+    location: String,
+    tagline: String,
+    message: Option<String>,
+    is_error: bool,
+}
+
+pub async fn get_form(query: Query<LocalFormQueryViewModel>, State(state): State<Arc<AppState>>) -> Response {
+    let mut vm = LocalFormViewModel::default();
+    // Prefill the form when editing an existing profile
+    if let Some(slug) = query.0.slug.filter(|slug| is_slug_valid(slug)) {
+        if let Ok(Some(profile)) = state.local_profile_service.get_by_slug(&slug).await {
+            vm = to_form_view_model(&profile);
+        }
+    }
+    render_form(&state, StatusCode::OK, &vm)
+}
+
+pub async fn post_form(State(state): State<Arc<AppState>>, mut multipart: Multipart) -> Response {
+    let mut vm = LocalFormViewModel::default();
+    let mut token = String::new();
+    let mut avatar: Option<Vec<u8>> = None;
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(_) => return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await
+        };
+        let field_name = field.name().unwrap_or("").to_string();
+        if field_name == "avatar" {
+            match field.bytes().await {
+                Ok(bytes) if !bytes.is_empty() => avatar = Some(bytes.to_vec()),
+                Ok(_) => (),
+                Err(_) => return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await
+            }
+            continue;
+        }
+        let value = match field.text().await {
+            Ok(value) => value.trim().to_string(),
+            Err(_) => return super::get_error_page(&state.registry, StatusCode::BAD_REQUEST).await
+        };
+        match field_name.as_str() {
+            "token" => token = value,
+            "slug" => vm.slug = value,
+            "name" => vm.name = value,
+            "pronouns" => vm.pronouns = value,
+            "location" => vm.location = value,
+            "tagline" => vm.tagline = value,
+            _ => ()
+        }
+    }
+
+    let is_token_valid = match &state.admin_token {
+        Some(admin_token) => super::is_token_equal(&token, admin_token),
+        None => false
+    };
+    if !is_token_valid {
+        vm.message = Some(String::from("The admin token is not valid."));
+        vm.is_error = true;
+        return render_form(&state, StatusCode::UNAUTHORIZED, &vm);
+    }
+
+    let profile_vm = LocalProfileViewModel {
+        name: vm.name.clone(),
+        pronouns: Some(vm.pronouns.clone()),
+        location: Some(vm.location.clone()),
+        tagline: Some(vm.tagline.clone()),
+        avatar: None,
+    };
+    let status_code = match save_profile(&state, &vm.slug, profile_vm, avatar).await {
+        Ok(profile) => {
+            vm = to_form_view_model(&profile);
+            vm.message = Some(format!("Saved the profile {}.", profile.slug));
+            StatusCode::OK
+        },
+        Err((status_code, message)) => {
+            vm.message = Some(message);
+            vm.is_error = true;
+            status_code
+        }
+    };
+    render_form(&state, status_code, &vm)
+}
+
+pub async fn get_profiles(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    match state.local_profile_service.get_all().await {
+        Ok(profiles) => Json(profiles).into_response(),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn get_profile(Path(slug): Path<String>, headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    if !is_slug_valid(&slug) {
+        return super::json_error(StatusCode::BAD_REQUEST, "Invalid slug");
+    }
+
+    match state.local_profile_service.get_by_slug(&slug).await {
+        Ok(Some(profile)) => Json(profile).into_response(),
+        Ok(None) => super::json_error(StatusCode::NOT_FOUND, "Local profile not found"),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub async fn put_profile(Path(slug): Path<String>, headers: HeaderMap, State(state): State<Arc<AppState>>, Json(vm): Json<LocalProfileViewModel>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    let avatar = match &vm.avatar {
+        Some(avatar) => match base64::engine::general_purpose::STANDARD.decode(avatar) {
+            Ok(bytes) => Some(bytes),
+            Err(_) => return super::json_error(StatusCode::BAD_REQUEST, "Avatar is not valid base64")
+        },
+        None => None
+    };
+    match save_profile(&state, &slug, vm, avatar).await {
+        Ok(profile) => Json(profile).into_response(),
+        Err((status_code, message)) => super::json_error(status_code, &message)
+    }
+}
+
+pub async fn delete_profile(Path(slug): Path<String>, headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    if !super::is_authorized(&headers, &state.admin_token) {
+        return super::json_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    if !is_slug_valid(&slug) {
+        return super::json_error(StatusCode::BAD_REQUEST, "Invalid slug");
+    }
+
+    match state.local_profile_service.delete(&slug).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => super::json_error(StatusCode::NOT_FOUND, "Local profile not found"),
+        Err(e) => super::json_app_error(&e)
+    }
+}
+
+pub fn is_slug_valid(slug: &str) -> bool {
+    validators::is_str_valid_length(slug, 1, 64) && validators::is_str_slug(slug)
+}
+
+// Validate and store a profile, resizing the avatar on the render pool.
+async fn save_profile(state: &AppState, slug: &str, vm: LocalProfileViewModel, avatar: Option<Vec<u8>>) -> Result<LocalProfile, (StatusCode, String)> {
+    if !is_slug_valid(slug) {
+        return Err((StatusCode::BAD_REQUEST, String::from("The slug may only contain lowercase letters, digits, - and _.")));
+    }
+    if !vm.is_valid() {
+        return Err((StatusCode::BAD_REQUEST, String::from("The profile has missing or too long fields.")));
+    }
+
+    let avatar = match avatar {
+        Some(avatar) if avatar.len() > MAX_AVATAR_BYTES => {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, String::from("The avatar is larger than 1 MiB.")));
+        },
+        Some(avatar) => {
+            let normalized = state.render_service
+                .render(move || card_renderer::normalize_avatar(&avatar, AVATAR_SIZE))
+                .await
+                .map_err(|_| (StatusCode::BAD_REQUEST, String::from("The avatar is not a supported image.")))?;
+            Some(normalized)
+        },
+        None => None
+    };
+
+    let non_empty = |value: Option<String>| value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let profile = LocalProfile {
+        slug: slug.to_string(),
+        name: vm.name.trim().to_string(),
+        pronouns: non_empty(vm.pronouns),
+        location: non_empty(vm.location),
+        tagline: non_empty(vm.tagline),
+        avatar,
+        updated: time::get_timestamp(),
+    };
+    // Only a profile that is not valid is the client's fault, storage failures are ours
+    state.local_profile_service.save(profile).await
+        .map_err(|e| match e {
+            AppError::Invalid(message) => (StatusCode::BAD_REQUEST, message),
+            e => {
+                log::error!("Failed to save local profile {}: {}", slug, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, String::from("Failed to save the local profile."))
+            }
+        })
+}
+
+fn to_form_view_model(profile: &LocalProfile) -> LocalFormViewModel {
+    LocalFormViewModel {
+        slug: profile.slug.clone(),
+        name: profile.name.clone(),
+        pronouns: profile.pronouns.clone().unwrap_or_default(),
+        location: profile.location.clone().unwrap_or_default(),
+        tagline: profile.tagline.clone().unwrap_or_default(),
+        message: None,
+        is_error: false,
+    }
+}
+
+fn render_form(state: &AppState, status_code: StatusCode, vm: &LocalFormViewModel) -> Response {
+    let data = super::TemplateViewModel {
+        title: "Local profile".into(),
+        body: state.registry.render("local", vm).unwrap(),
+    };
+
+    let r = state.registry.render("template", &data).unwrap();
+    (status_code, Html(r)).into_response()
+}
@@ -0,0 +1,72 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_rusqlite::Connection;
+
+
+pub static MEMORY_PATH: &str = ":memory:";
+
+// Connections to the database, a single one for writes and a few for reads so lookups are not queued behind writes.
+// In WAL mode readers see the last committed write while it is being written.
+#[derive(Clone)]
+pub struct Database {
+    writer: Connection,
+    readers: Arc<Vec<Connection>>,
+    next_reader: Arc<AtomicUsize>,
+}
+
+impl Database {
+    pub async fn open(path: &str, readers: usize, busy_timeout: Duration) -> Result<Database, tokio_rusqlite::Error> {
+        let writer = Connection::open(path).await?;
+        writer.call(move |conn| {
+            conn.busy_timeout(busy_timeout)?;
+            let journal_mode: String = conn.query_row("PRAGMA journal_mode = WAL", (), |row| row.get(0))?;
+            conn.pragma_update(None, "synchronous", "NORMAL")?;
+            log::info!("Database journal mode: {}", journal_mode);
+            Ok(())
+        }).await?;
+
+        // An in-memory database is private to its connection, so reads go through the writer
+        if path == MEMORY_PATH {
+            return Ok(Database {
+                readers: Arc::new(vec![writer.clone()]),
+                writer,
+                next_reader: Arc::new(AtomicUsize::new(0)),
+            });
+        }
+
+        let mut reader_conns = Vec::new();
+        for _ in 0..readers.max(1) {
+            let reader = Connection::open(path).await?;
+            reader.call(move |conn| {
+                conn.busy_timeout(busy_timeout)?;
+                conn.pragma_update(None, "query_only", true)?;
+                Ok(())
+            }).await?;
+            reader_conns.push(reader);
+        }
+        Ok(Database {
+            writer,
+            readers: Arc::new(reader_conns),
+            next_reader: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    // Run a query on the next reader in turn
+    pub async fn read<F, R>(&self, function: F) -> Result<R, tokio_rusqlite::Error>
+    where
+        F: FnOnce(&mut rusqlite::Connection) -> rusqlite::Result<R> + 'static + Send,
+        R: Send + 'static,
+    {
+        let index = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.readers[index].call(function).await
+    }
+
+    pub async fn write<F, R>(&self, function: F) -> Result<R, tokio_rusqlite::Error>
+    where
+        F: FnOnce(&mut rusqlite::Connection) -> rusqlite::Result<R> + 'static + Send,
+        R: Send + 'static,
+    {
+        self.writer.call(function).await
+    }
+}
\ No newline at end of file
@@ -1 +1,6 @@
-pub mod github_user;
\ No newline at end of file
+pub mod avatar_cache;
+pub mod github_org;
+pub mod github_repo;
+pub mod github_user;
+pub mod local_profile;
+pub mod missing_user;
\ No newline at end of file
@@ -0,0 +1,9 @@
+
+#[derive(Debug, Clone)]
+pub struct AvatarCache {
+    pub key: String,
+    pub data: Vec<u8>,
+    pub size: i64,
+    pub expiration: i64,
+    pub last_used: i64,
+}
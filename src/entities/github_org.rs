@@ -0,0 +1,14 @@
+
+#[derive(Debug, Clone)]
+pub struct GithubOrg {
+    pub id: i32,
+    pub login: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub blog: Option<String>,
+    pub public_repos: i64,
+    pub public_members: i64,
+    pub avatar_url: String,
+    pub expiration: i64,
+}
@@ -0,0 +1,17 @@
+
+#[derive(Debug, Clone)]
+pub struct GithubRepo {
+    pub id: i64,
+    pub owner_id: i32,
+    pub owner_login: String,
+    pub owner_avatar_url: String,
+    pub name: String,
+    pub full_name: String,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub stars: i64,
+    pub forks: i64,
+    pub license_spdx_id: Option<String>,
+    pub license_name: Option<String>,
+    pub expiration: i64,
+}
@@ -1,10 +1,18 @@
 
 #[derive(Debug, Clone)]
 pub struct GithubUser {
-    pub id: i32,
+    pub provider: String,
+    pub id: i64,
     pub username: String,
     pub name: Option<String>,
     pub location: Option<String>,
     pub avatar_url: String,
+    pub pronouns: Option<String>,
+    pub tagline: Option<String>,
+    // Names of the pinned repositories, one per line
+    pub pinned: Option<String>,
+    pub sponsors: Option<i64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
     pub expiration: i64,
 }
@@ -0,0 +1,11 @@
+
+#[derive(Debug, Clone)]
+pub struct LocalProfile {
+    pub slug: String,
+    pub name: String,
+    pub pronouns: Option<String>,
+    pub location: Option<String>,
+    pub tagline: Option<String>,
+    pub avatar: Option<Vec<u8>>,
+    pub updated: i64,
+}
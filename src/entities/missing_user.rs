@@ -0,0 +1,7 @@
+
+#[derive(Debug, Clone)]
+pub struct MissingUser {
+    pub provider: String,
+    pub username: String,
+    pub expiration: i64,
+}
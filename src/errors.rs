@@ -0,0 +1,59 @@
+use std::error::Error;
+use std::fmt;
+
+
+// Failures of fetching, caching and storing profiles, mapped to HTTP statuses by the controllers.
+#[derive(Debug, Clone)]
+pub enum AppError {
+    // The user, organisation or repository does not exist
+    NotFound(String),
+    // The request can't be served as asked, such as an unknown provider
+    Invalid(String),
+    // The provider is rate limiting us until the reset timestamp, in seconds
+    RateLimited { reset: i64 },
+    // The provider is unreachable or answered with an unexpected status
+    Upstream(String),
+    // The provider failed repeatedly and is not called until the reset timestamp, in seconds
+    Unavailable { reset: i64 },
+    // A response or image could not be decoded
+    Decode(String),
+    // The database failed
+    Storage(String),
+    // Our own setup failed, such as a GitHub App key that can't sign
+    Internal(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound(message) => write!(f, "Not found: {}", message),
+            AppError::Invalid(message) => write!(f, "Invalid: {}", message),
+            AppError::RateLimited { reset } => write!(f, "Rate limited until {}", reset),
+            AppError::Upstream(message) => write!(f, "Upstream error: {}", message),
+            AppError::Unavailable { reset } => write!(f, "Unavailable until {}", reset),
+            AppError::Decode(message) => write!(f, "Decode error: {}", message),
+            AppError::Storage(message) => write!(f, "Storage error: {}", message),
+            AppError::Internal(message) => write!(f, "Internal error: {}", message),
+        }
+    }
+}
+
+impl Error for AppError {}
+
+impl From<reqwest::Error> for AppError {
+    fn from(err: reqwest::Error) -> Self {
+        AppError::Upstream(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Decode(err.to_string())
+    }
+}
+
+impl From<tokio_rusqlite::Error> for AppError {
+    fn from(err: tokio_rusqlite::Error) -> Self {
+        AppError::Storage(err.to_string())
+    }
+}
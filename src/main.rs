@@ -1,33 +1,130 @@
-use std::{sync::{Arc}, net::{SocketAddr, IpAddr, Ipv4Addr}, str::FromStr};
-use clap::Parser;
-use axum::{routing::get, Router};
+use std::{collections::HashMap, sync::{Arc}, net::{SocketAddr, IpAddr, Ipv4Addr}, str::FromStr, time::Duration};
+use clap::{Parser, Subcommand};
+use axum::{routing::{get, post}, Router};
 use axum::http::{Response, StatusCode};
 use axum::body::{boxed, Body};
 use handlebars::Handlebars;
+use mappers::language_color_mapper::LanguageColorMapper;
 use mappers::pronouns_mapper::PronounsMapper;
+use providers::fediverse_provider::FediverseProvider;
+use providers::gitea_provider::GiteaProvider;
+use providers::circuit_breaker::CircuitBreaker;
+use providers::fixtures::FixtureStore;
+use providers::github_provider::GithubProvider;
+use providers::gitlab_provider::GitlabProvider;
+use providers::profile_provider::ProfileProvider;
+use providers::public_address::{self, PublicResolver};
+use providers::rate_limit::RateLimitState;
+use providers::retry::RetryPolicy;
+use models::cached_user::CachedUser;
+use services::avatar_cache_service::AvatarCacheService;
+use services::avatar_service::AvatarService;
+use services::github_api_service::GithubApiService;
+use services::github_auth_service::{GithubAuth, GithubAuthService};
+use services::github_org_service::GithubOrgService;
+use services::github_repo_service::GithubRepoService;
 use services::github_user_service::GithubUserService;
-use tokio::sync::Mutex;
-use tokio_rusqlite::{Connection};
+use services::local_profile_service::LocalProfileService;
+use services::missing_user_service::MissingUserService;
+use services::refresh_service::RefreshService;
+use services::render_service::RenderService;
 use tower::{ServiceBuilder, ServiceExt};
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
-use reqwest::Client;
+use reqwest::{Certificate, Client, ClientBuilder, NoProxy, Proxy};
+use chrono::{TimeZone, Utc};
+use config::{Config, HttpConfig};
+use database::Database;
+use errors::AppError;
 
 pub mod models;
+pub mod config;
 pub mod controllers;
+pub mod database;
 pub mod entities;
+pub mod errors;
 pub mod repositories;
 pub mod services;
 pub mod mappers;
+pub mod migrations;
+pub mod providers;
+pub mod renderers;
 pub mod time;
 pub mod validators;
 
-use controllers::{index, image};
+use controllers::{admin, health, index, image, local};
+use repositories::avatar_cache_repository::AvatarCacheRepository;
+use repositories::github_org_repository::GithubOrgRepository;
+use repositories::github_repo_repository::GithubRepoRepository;
 use repositories::github_user_repository::GithubUserRepository;
+use repositories::memory_user_repository::MemoryUserRepository;
+use repositories::local_profile_repository::LocalProfileRepository;
+use repositories::missing_user_repository::MissingUserRepository;
+use repositories::user_repository::UserRepository;
 
 static TABLE_GITHUB_USER: &str = "GithubUser";
+static TABLE_GITHUB_ORG: &str = "GithubOrg";
+static TABLE_GITHUB_REPO: &str = "GithubRepo";
+static TABLE_LOCAL_PROFILE: &str = "LocalProfile";
+static TABLE_AVATAR_CACHE: &str = "AvatarCache";
+static TABLE_MISSING_USER: &str = "MissingUser";
 
 
+// Where cached profiles are kept
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum UserStore {
+    Sqlite,
+    // Lost on restart, for tests and ephemeral deployments
+    Memory,
+}
+
+// Tasks run instead of the server
+#[derive(Subcommand, Debug)]
+enum Command {
+    // Apply the pending database migrations and exit
+    Migrate {
+        // Print the pending migrations without applying them
+        #[clap(long = "dry-run")]
+        dry_run: bool,
+    },
+    // Inspect and fix the cached users
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    // List the cached users with their expiration
+    List {
+        #[clap(long = "provider")]
+        provider: Option<String>,
+    },
+    // Print a cached user
+    Show {
+        provider: String,
+        username: String,
+    },
+    // Fetch a user again, even if it is fresh or remembered as missing
+    Refresh {
+        provider: String,
+        username: String,
+    },
+    // Purge a cached user, every cached user or the expired ones
+    Purge {
+        #[clap(requires = "username", required_unless_present_any = ["all", "expired"], conflicts_with_all = ["all", "expired"])]
+        provider: Option<String>,
+        username: Option<String>,
+        #[clap(long = "all", conflicts_with = "expired")]
+        all: bool,
+        #[clap(long = "expired")]
+        expired: bool,
+    },
+    // Give the space of purged users back to the file system
+    Vacuum,
+}
+
 // Command line interface
 #[derive(Parser, Debug)]
 #[clap(name="smol-profile-card", about="Another image generator server!")]
@@ -43,12 +140,179 @@ struct Opt {
 
     #[clap(long = "static_dir", default_value = "static")]
     static_dir: String,
+
+    // JSON file of settings, overridden by the flags given on the command line
+    #[clap(long = "config", env = "SMOL_CONFIG")]
+    config: Option<String>,
+
+    // SQLite database file, or :memory: for a cache lost on restart
+    #[clap(long = "db", default_value = "db.sqlite")]
+    db: String,
+
+    // Connections used for reads alongside the single one used for writes
+    #[clap(long = "db_readers", default_value = "4")]
+    db_readers: usize,
+
+    // Milliseconds a connection waits for a lock held by another one before failing
+    #[clap(long = "db_busy_timeout", default_value = "5000")]
+    db_busy_timeout: u64,
+
+    #[clap(long = "render_workers", default_value = "4")]
+    render_workers: usize,
+
+    #[clap(long = "render_queue", default_value = "64")]
+    render_queue: usize,
+
+    #[clap(long = "gitlab_url", default_value = "https://gitlab.com")]
+    gitlab_url: String,
+
+    #[clap(long = "gitea_url", default_value = "https://gitea.com")]
+    gitea_url: String,
+
+    #[clap(long = "forgejo_url", default_value = "https://codeberg.org")]
+    forgejo_url: String,
+
+    // Seconds to wait for a provider to accept a connection and to answer a whole request
+    #[clap(long = "connect_timeout", default_value = "5")]
+    connect_timeout: u64,
+
+    #[clap(long = "request_timeout", default_value = "10")]
+    request_timeout: u64,
+
+    // Proxy for every outbound request, such as http://proxy:3128 or socks5://proxy:1080
+    #[clap(long = "proxy")]
+    proxy: Option<String>,
+
+    #[clap(long = "http_proxy")]
+    http_proxy: Option<String>,
+
+    #[clap(long = "https_proxy")]
+    https_proxy: Option<String>,
+
+    // Comma separated hosts, domains and IP ranges reached without the proxy
+    #[clap(long = "no_proxy")]
+    no_proxy: Option<String>,
+
+    // PEM bundle of extra root certificates, such as the CA of a TLS intercepting proxy. May be repeated.
+    #[clap(long = "ca_file")]
+    ca_file: Vec<String>,
+
+    #[clap(long = "user_agent")]
+    user_agent: Option<String>,
+
+    // Use http to resolve fediverse handles against a local stub server
+    #[clap(long = "fediverse_scheme", default_value = "https")]
+    fediverse_scheme: String,
+
+    // Allow fediverse instances on loopback and private addresses, which are otherwise refused. Only for a local stub server.
+    #[clap(long = "fediverse_allow_private")]
+    fediverse_allow_private: bool,
+
+    // Base URL of the Gravatar API, such as a local mirror
+    #[clap(long = "gravatar_url", default_value = "https://gravatar.com/avatar")]
+    gravatar_url: String,
+
+    #[clap(long = "libravatar_url", default_value = "https://seccdn.libravatar.org/avatar")]
+    libravatar_url: String,
+
+    // Total size of the cached avatars in MiB, least recently used avatars are evicted past it
+    #[clap(long = "avatar_cache_size", default_value = "64")]
+    avatar_cache_size: i64,
+
+    #[clap(long = "user_store", value_enum, default_value = "sqlite")]
+    user_store: UserStore,
+
+    // Seconds a username the provider doesn't know is remembered before it is looked up again
+    #[clap(long = "missing_ttl", default_value = "3600")]
+    missing_ttl: i64,
+
+    // Seconds past expiration a cached profile is still served while it is refreshed
+    #[clap(long = "max_stale", default_value = "604800")]
+    max_stale: i64,
+
+    // Seconds between runs of the background refresh of popular profiles, 0 disables it
+    #[clap(long = "refresh_interval", default_value = "60")]
+    refresh_interval: u64,
+
+    // Number of most requested profiles kept fresh
+    #[clap(long = "refresh_top", default_value = "50")]
+    refresh_top: usize,
+
+    // Seconds before expiration a popular profile is refreshed
+    #[clap(long = "refresh_ahead", default_value = "600")]
+    refresh_ahead: i64,
+
+    // Requests of the rate limit left to foreground misses
+    #[clap(long = "refresh_reserve", default_value = "100")]
+    refresh_reserve: i64,
+
+    // Base URLs of the GitHub API and avatars, such as a GitHub Enterprise Server or a local mock
+    #[clap(long = "github_api_url", default_value = "https://api.github.com")]
+    github_api_url: String,
+
+    #[clap(long = "github_avatars_url", default_value = "https://avatars.githubusercontent.com")]
+    github_avatars_url: String,
+
+    // Attempts of a GitHub request failing with a connection error or 5xx response
+    #[clap(long = "github_retries", default_value = "3")]
+    github_retries: u32,
+
+    // Consecutive failed GitHub requests that stop calls to GitHub for the cooldown in seconds
+    #[clap(long = "breaker_threshold", default_value = "5")]
+    breaker_threshold: u32,
+
+    #[clap(long = "breaker_cooldown", default_value = "30")]
+    breaker_cooldown: i64,
+
+    // Serve GitHub profiles, organisations, repositories and avatars from a directory, for demos and tests without network
+    #[clap(long = "fixtures")]
+    fixtures: Option<String>,
+
+    // Fetch from GitHub as usual and store the responses into the fixtures directory
+    #[clap(long = "record", requires = "fixtures")]
+    record: bool,
+
+    // Fetch GitHub profiles with a single GraphQL query, which needs a token or GitHub App
+    #[clap(long = "github_graphql")]
+    github_graphql: bool,
+
+    // Personal access token for the GitHub API, which raises the rate limit
+    #[clap(long = "github_token", env = "GITHUB_TOKEN", hide_env_values = true)]
+    github_token: Option<String>,
+
+    #[clap(long = "github_token_file", env = "GITHUB_TOKEN_FILE")]
+    github_token_file: Option<String>,
+
+    // Authenticate as a GitHub App installation instead of with a token
+    #[clap(long = "github_app_id", env = "GITHUB_APP_ID")]
+    github_app_id: Option<String>,
+
+    #[clap(long = "github_app_installation_id", env = "GITHUB_APP_INSTALLATION_ID")]
+    github_app_installation_id: Option<i64>,
+
+    #[clap(long = "github_app_key_file", env = "GITHUB_APP_KEY_FILE")]
+    github_app_key_file: Option<String>,
+
+    // Bearer token for the admin API, which is disabled when unset
+    #[clap(long = "admin_token", env = "SMOL_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
 }
 
 pub struct AppState {
     registry: Handlebars<'static>,
-    github_user_service: GithubUserService,
-    pronouns_mapper: PronounsMapper
+    github_api_service: Arc<GithubApiService>,
+    github_user_service: Arc<GithubUserService>,
+    github_org_service: GithubOrgService,
+    github_repo_service: GithubRepoService,
+    local_profile_service: LocalProfileService,
+    refresh_service: Arc<RefreshService>,
+    render_service: RenderService,
+    pronouns_mapper: PronounsMapper,
+    language_color_mapper: LanguageColorMapper,
+    admin_token: Option<String>
 }
 
 #[tokio::main]
@@ -61,65 +325,228 @@ async fn main() {
     // Enable console logging
     tracing_subscriber::fmt::init();
 
+    if let Some(Command::Migrate { dry_run }) = opt.command {
+        migrate(&opt, dry_run).await;
+        return;
+    }
+
+    let config = match &opt.config {
+        Some(path) => Config::load(path).unwrap_or_else(|err| {
+            panic!("Failed to load the config file {}!\n{:?}", path, err);
+        }),
+        None => Config::default()
+    };
+
     // Register templates
     let mut handlebars = Handlebars::new();
     handlebars.set_strict_mode(true);
     handlebars.register_template_string("template", include_str!("templates/template.hbs")).unwrap();
     handlebars.register_template_string("index", include_str!("templates/index.hbs")).unwrap();
     handlebars.register_template_string("about", include_str!("templates/about.hbs")).unwrap();
+    handlebars.register_template_string("local", include_str!("templates/local.hbs")).unwrap();
     handlebars.register_template_string("errors/template", include_str!("templates/errors/template.hbs")).unwrap();
 
-    // Create database connection
-    let conn = Connection::open("db.sqlite").await.unwrap_or_else(|err| {
-        panic!("Failed to create a connection to database!\n{:?}", err);
-    });
-    conn.call(|conn| {
-        let query = format!("CREATE TABLE IF NOT EXISTS {} (
-            id          INTEGER PRIMARY KEY,
-            username    TEXT NOT NULL COLLATE NOCASE,
-            name        TEXT,
-            location    TEXT,
-            avatar_url  TEXT NOT NULL,
-            expiration  INTEGER NOT NULL
-        )", TABLE_GITHUB_USER);
-        conn.execute(query.as_str(),()).unwrap_or_else(|err| {
-            panic!("Failed to create table for GitHubUser!\n{:?}", err);
+    // Create database connections
+    let db = open_database(&opt).await;
+    log::info!("Database: {}", opt.db);
+    let applied = db.write(|conn| Ok(migrations::run(conn))).await
+        .map_err(AppError::from)
+        .and_then(|result| result)
+        .unwrap_or_else(|err| {
+            panic!("Failed to migrate the database!\n{:?}", err);
         });
-
-        Ok(())
-    }).await.unwrap_or_else(|err| {
-        panic!("Something went wrong!\n{:?}", err);
-    });
+    if applied.is_empty() {
+        log::info!("Database is at version {}", migrations::get_latest_version());
+    }
 
     // Create reqwest client
-    let client = Client::new();
+    let http_config = config.http.merge(HttpConfig {
+        proxy: opt.proxy.clone(),
+        http_proxy: opt.http_proxy.clone(),
+        https_proxy: opt.https_proxy.clone(),
+        no_proxy: opt.no_proxy.clone(),
+        ca_files: opt.ca_file.clone(),
+        user_agent: opt.user_agent.clone(),
+    });
+    let client = build_client(&opt, &http_config).unwrap_or_else(|err| {
+        panic!("Failed to create the HTTP client!\n{:?}", err);
+    });
+    // Fediverse instances are named by users, so requests to them may only reach public addresses
+    let fediverse_client = if opt.fediverse_allow_private {
+        client.clone()
+    } else {
+        get_client_builder(&opt, &http_config)
+            .and_then(|(builder, _)| Ok(builder
+                .dns_resolver(Arc::new(PublicResolver))
+                .redirect(public_address::redirect_policy())
+                .build()?))
+            .unwrap_or_else(|err| {
+                panic!("Failed to create the fediverse HTTP client!\n{:?}", err);
+            })
+    };
 
     // Setup repositories
-    let github_user_repository = GithubUserRepository {
-        conn: conn.clone()
+    let github_user_repository: Arc<dyn UserRepository> = match opt.user_store {
+        UserStore::Sqlite => Arc::new(GithubUserRepository {
+            db: db.clone()
+        }),
+        UserStore::Memory => Arc::new(MemoryUserRepository::default()),
+    };
+    log::info!("User store: {:?}", opt.user_store);
+    let github_org_repository = GithubOrgRepository {
+        db: db.clone()
+    };
+    let github_repo_repository = GithubRepoRepository {
+        db: db.clone()
+    };
+    let local_profile_repository = LocalProfileRepository {
+        db: db.clone()
+    };
+    let avatar_cache_repository = AvatarCacheRepository {
+        db: db.clone()
+    };
+    let missing_user_repository = MissingUserRepository {
+        db: db.clone()
     };
 
     // Setup services
-    let github_user_service = GithubUserService {
-        client: Arc::new(Mutex::new(client.clone())),
+    let github_api_url = opt.github_api_url.trim_end_matches('/').to_string();
+    let github_auth_service = GithubAuthService::new(get_github_auth(&opt), github_api_url.clone(), client.clone());
+    log::info!("GitHub API: {}, auth: {}", github_api_url, github_auth_service.mode());
+    let github_graphql_url = get_github_graphql_url(&github_api_url);
+    let github_api_service = Arc::new(GithubApiService {
+        client: client.clone(),
+        rate_limit: RateLimitState::default(),
+        graphql_rate_limit: RateLimitState::default(),
+        base_url: github_api_url,
+        graphql_url: github_graphql_url,
+        auth: github_auth_service,
+        retry: RetryPolicy::new(opt.github_retries),
+        breaker: CircuitBreaker::new(opt.breaker_threshold, opt.breaker_cooldown),
+        fixtures: opt.fixtures.as_deref().map(|dir| FixtureStore::new(dir, opt.record)),
+    });
+    if let Some(fixtures) = &github_api_service.fixtures {
+        let mode = if fixtures.record { "recording into" } else { "serving" };
+        log::info!("GitHub fixtures: {} {}", mode, fixtures.dir.display());
+    }
+
+    // Setup profile providers
+    let github_provider = Arc::new(GithubProvider {
+        api: github_api_service.clone(),
         image_client: Arc::new(client.clone()),
-        repository: github_user_repository,
-        remaining: Arc::new(Mutex::new(0)),
-        reset: Arc::new(Mutex::new(0)),
-        retry_after: Arc::new(Mutex::new(0))
+        avatars_url: opt.github_avatars_url.trim_end_matches('/').to_string(),
+        graphql: use_github_graphql(&opt, &github_api_service),
+    });
+    let github_user_service = Arc::new(GithubUserService::new(
+        github_user_repository,
+        github_provider.clone(),
+        HashMap::from([
+            (String::from("github"), github_provider as Arc<dyn ProfileProvider>),
+            (String::from("gitlab"), Arc::new(GitlabProvider {
+                base_url: opt.gitlab_url.trim_end_matches('/').to_string(),
+                client: Arc::new(client.clone()),
+                rate_limit: RateLimitState::default(),
+            })),
+            (String::from("gitea"), Arc::new(GiteaProvider {
+                name: String::from("gitea"),
+                base_url: opt.gitea_url.trim_end_matches('/').to_string(),
+                client: Arc::new(client.clone()),
+                rate_limit: RateLimitState::default(),
+            })),
+            (String::from("forgejo"), Arc::new(GiteaProvider {
+                name: String::from("forgejo"),
+                base_url: opt.forgejo_url.trim_end_matches('/').to_string(),
+                client: Arc::new(client.clone()),
+                rate_limit: RateLimitState::default(),
+            })),
+            (String::from("fediverse"), Arc::new(FediverseProvider {
+                scheme: opt.fediverse_scheme.clone(),
+                client: Arc::new(fediverse_client),
+                allow_private: opt.fediverse_allow_private,
+            })),
+        ]),
+        AvatarService {
+            client: Arc::new(client.clone()),
+            gravatar_url: opt.gravatar_url.trim_end_matches('/').to_string(),
+            libravatar_url: opt.libravatar_url.trim_end_matches('/').to_string(),
+        },
+        AvatarCacheService {
+            repository: avatar_cache_repository,
+            max_bytes: opt.avatar_cache_size * 1024 * 1024,
+        },
+        MissingUserService {
+            repository: missing_user_repository,
+            ttl: opt.missing_ttl * 1000,
+        },
+        opt.max_stale * 1000,
+    ));
+
+    if let Some(Command::Cache { command }) = &opt.command {
+        run_cache_command(&github_user_service, command).await;
+        return;
+    }
+    let github_org_service = GithubOrgService {
+        api: github_api_service.clone(),
+        repository: github_org_repository,
+    };
+    let github_repo_service = GithubRepoService {
+        api: github_api_service.clone(),
+        repository: github_repo_repository,
+    };
+    let local_profile_service = LocalProfileService {
+        repository: local_profile_repository,
     };
 
+    let refresh_service = Arc::new(RefreshService::new(
+        github_user_service.clone(),
+        opt.refresh_interval,
+        opt.refresh_top,
+        opt.refresh_ahead * 1000,
+        opt.refresh_reserve,
+    ));
+    tokio::spawn(refresh_service.clone().run());
+
+    if opt.admin_token.is_none() {
+        log::warn!("No admin token is set, the admin API is disabled!");
+    }
+
+    let render_service = RenderService::new(opt.render_workers, opt.render_queue);
+    log::info!("Render pool: {} workers, queue depth {}", render_service.workers, render_service.queue_depth);
+
     // Setup controller routes and inject app state
     let app_state = Arc::new(AppState { 
         registry: handlebars,
+        github_api_service,
         github_user_service,
+        github_org_service,
+        github_repo_service,
+        local_profile_service,
+        refresh_service,
+        render_service,
         pronouns_mapper: PronounsMapper::new(),
+        language_color_mapper: LanguageColorMapper::new(),
+        admin_token: opt.admin_token,
     });
     let app = Router::new()
         .route("/", get(index::get_index))
         .route("/about", get(index::get_about))
+        .route("/health", get(health::get_health))
         .route("/image", get(image::get_index))
         .route("/image/html", get(image::get_html))
+        .route("/image/team", get(image::get_team))
+        .route("/image/org", get(image::get_org))
+        .route("/image/repo", get(image::get_repo))
+        .route("/local", get(local::get_form).post(local::post_form))
+        .route("/api/local", get(local::get_profiles))
+        .route("/api/local/:slug", get(local::get_profile)
+            .put(local::put_profile)
+            .delete(local::delete_profile))
+        .route("/api/admin/refresh", get(admin::get_refresh_status))
+        .route("/api/admin/avatars", get(admin::get_avatar_cache).delete(admin::delete_avatar_cache))
+        .route("/api/admin/users", get(admin::get_users).delete(admin::delete_users))
+        .route("/api/admin/users/:provider/:username", get(admin::get_user).delete(admin::delete_user))
+        .route("/api/admin/users/:provider/:username/refresh", post(admin::post_user_refresh))
+        .route("/api/admin/vacuum", post(admin::post_vacuum))
         .fallback_service(get(|req| async move {
             match ServeDir::new(opt.static_dir).oneshot(req).await {
                 Ok(res) => res.map(boxed),
@@ -143,3 +570,230 @@ async fn main() {
         .await
         .unwrap();
 }
+
+// Pick the GitHub auth mode from the options, preferring a GitHub App over a token
+fn get_github_auth(opt: &Opt) -> GithubAuth {
+    if let Some(app_id) = &opt.github_app_id {
+        let installation_id = opt.github_app_installation_id.unwrap_or_else(|| {
+            panic!("A GitHub App requires --github_app_installation_id!");
+        });
+        let key_file = opt.github_app_key_file.as_ref().unwrap_or_else(|| {
+            panic!("A GitHub App requires --github_app_key_file!");
+        });
+        let pem = std::fs::read(key_file).unwrap_or_else(|err| {
+            panic!("Failed to read the GitHub App key file {}!\n{:?}", key_file, err);
+        });
+        let key = openssl::pkey::PKey::private_key_from_pem(&pem).unwrap_or_else(|err| {
+            panic!("Failed to parse the GitHub App key file {}!\n{:?}", key_file, err);
+        });
+        return GithubAuth::App { app_id: app_id.clone(), installation_id, key };
+    }
+
+    let token = match (&opt.github_token, &opt.github_token_file) {
+        (Some(token), _) => Some(token.clone()),
+        (None, Some(token_file)) => Some(std::fs::read_to_string(token_file).unwrap_or_else(|err| {
+            panic!("Failed to read the GitHub token file {}!\n{:?}", token_file, err);
+        })),
+        (None, None) => None
+    };
+    match token.map(|token| token.trim().to_string()).filter(|token| !token.is_empty()) {
+        Some(token) => GithubAuth::Token(token),
+        None => GithubAuth::Anonymous
+    }
+}
+
+// Build the HTTP client shared by every provider, going through the configured proxies and trusting the extra root certificates
+fn build_client(opt: &Opt, http_config: &HttpConfig) -> Result<Client, Box<dyn std::error::Error>> {
+    let (builder, certificates) = get_client_builder(opt, http_config)?;
+
+    // Proxy URLs may hold credentials, so only whether they are set is logged
+    let (http_proxy, https_proxy, proxy) = get_proxies(http_config);
+    let proxies = [&proxy, &http_proxy, &https_proxy]
+        .iter()
+        .filter(|proxy| proxy.is_some())
+        .count();
+    log::info!("HTTP client: user agent {}, proxies: {}, extra root certificates: {}", http_config.get_user_agent(), proxies, certificates);
+    if http_config.no_proxy.is_some() && proxies == 0 {
+        log::warn!("No proxy is set, so the hosts to reach without one have no effect");
+    }
+
+    Ok(builder.build()?)
+}
+
+// Client settings shared by every client, such as timeouts, proxies and root certificates, with the number of certificates added
+fn get_client_builder(opt: &Opt, http_config: &HttpConfig) -> Result<(ClientBuilder, usize), Box<dyn std::error::Error>> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(opt.connect_timeout))
+        .timeout(Duration::from_secs(opt.request_timeout))
+        .user_agent(http_config.get_user_agent());
+
+    // Proxies are tried in order, so the ones for a single scheme come first.
+    // Without any, the proxies of the HTTP_PROXY, HTTPS_PROXY and NO_PROXY environment variables are used.
+    let no_proxy = http_config.no_proxy.as_deref().and_then(NoProxy::from_string);
+    let (http_proxy, https_proxy, proxy) = get_proxies(http_config);
+    if let Some(url) = &http_proxy {
+        builder = builder.proxy(Proxy::http(url)?.no_proxy(no_proxy.clone()));
+    }
+    if let Some(url) = &https_proxy {
+        builder = builder.proxy(Proxy::https(url)?.no_proxy(no_proxy.clone()));
+    }
+    if let Some(url) = &proxy {
+        builder = builder.proxy(Proxy::all(url)?.no_proxy(no_proxy.clone()));
+    }
+
+    let mut certificates = 0;
+    for path in &http_config.ca_files {
+        let pem = std::fs::read_to_string(path)?;
+        let bundle = config::split_pem_bundle(&pem);
+        if bundle.is_empty() {
+            return Err(format!("No certificates found in {}", path).into());
+        }
+        for certificate in bundle {
+            builder = builder.add_root_certificate(Certificate::from_pem(certificate.as_bytes())?);
+            certificates += 1;
+        }
+    }
+
+    Ok((builder, certificates))
+}
+
+// Get the HTTP, HTTPS and all scheme proxies. Hosts to skip only apply to proxies set on the client,
+// so with hosts to skip and no proxy given the ones of the environment are set on the client in their place.
+fn get_proxies(http_config: &HttpConfig) -> (Option<String>, Option<String>, Option<String>) {
+    let proxies = (http_config.http_proxy.clone(), http_config.https_proxy.clone(), http_config.proxy.clone());
+    if proxies != (None, None, None) || http_config.no_proxy.is_none() {
+        return proxies;
+    }
+    (get_env_proxy("http_proxy"), get_env_proxy("https_proxy"), get_env_proxy("all_proxy"))
+}
+
+// Get a proxy from the environment the way curl does, with the lowercase variable taking precedence
+fn get_env_proxy(name: &str) -> Option<String> {
+    [name.to_string(), name.to_uppercase()]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|url| !url.trim().is_empty())
+}
+
+// The GraphQL API is next to the REST API, at /api/graphql on a GitHub Enterprise Server
+fn get_github_graphql_url(api_url: &str) -> String {
+    match api_url.strip_suffix("/v3") {
+        Some(base_url) => format!("{}/graphql", base_url),
+        None => format!("{}/graphql", api_url)
+    }
+}
+
+// GraphQL is only used when asked for and authenticated. Fixtures are keyed by URL, which every query shares.
+fn use_github_graphql(opt: &Opt, api: &GithubApiService) -> bool {
+    if !opt.github_graphql {
+        return false;
+    }
+    if matches!(api.auth.auth, GithubAuth::Anonymous) {
+        log::warn!("The GitHub GraphQL API needs a token or GitHub App, using the REST API!");
+        return false;
+    }
+    if api.fixtures.is_some() {
+        log::warn!("GitHub fixtures are served from the REST API, using the REST API!");
+        return false;
+    }
+    log::info!("GitHub profiles are fetched from {}", api.graphql_url);
+    true
+}
+
+async fn open_database(opt: &Opt) -> Database {
+    Database::open(&opt.db, opt.db_readers, Duration::from_millis(opt.db_busy_timeout)).await.unwrap_or_else(|err| {
+        panic!("Failed to create a connection to database {}!\n{:?}", opt.db, err);
+    })
+}
+
+// Print the pending migrations, applying them unless it is a dry run
+async fn migrate(opt: &Opt, dry_run: bool) {
+    let db = open_database(opt).await;
+    let (version, pending) = db.write(|conn| {
+        let version = migrations::get_version(conn)?;
+        let pending = migrations::get_pending(conn)
+            .map(|pending| pending.iter().map(|migration| (migration.version, migration.description)).collect::<Vec<_>>());
+        Ok((version, pending))
+    }).await.unwrap_or_else(|err| {
+        panic!("Failed to read the database version!\n{:?}", err);
+    });
+    let pending = pending.unwrap_or_else(|err| {
+        panic!("Failed to migrate the database!\n{:?}", err);
+    });
+
+    println!("Database {} is at version {}, latest is {}", opt.db, version, migrations::get_latest_version());
+    if pending.is_empty() {
+        println!("No pending migrations");
+        return;
+    }
+    for (version, description) in &pending {
+        println!("  {}: {}", version, description);
+    }
+    if dry_run {
+        println!("{} pending migration(s), none applied", pending.len());
+        return;
+    }
+
+    db.write(|conn| Ok(migrations::run(conn))).await
+        .map_err(AppError::from)
+        .and_then(|result| result)
+        .unwrap_or_else(|err| {
+            panic!("Failed to migrate the database!\n{:?}", err);
+        });
+    println!("Applied {} migration(s)", pending.len());
+}
+
+// Run a cache command against the users of the database, exiting with an error when the user is not found
+async fn run_cache_command(service: &GithubUserService, command: &CacheCommand) {
+    let fail = |err: AppError| -> ! {
+        panic!("Failed to run the cache command!\n{:?}", err);
+    };
+    let not_found = |provider: &str, username: &str| -> ! {
+        eprintln!("No {} user {}", provider, username);
+        std::process::exit(1);
+    };
+    let print_user = |user: Option<CachedUser>, provider: &str, username: &str| match user {
+        Some(user) => println!("{}", serde_json::to_string_pretty(&user).unwrap()),
+        None => not_found(provider, username)
+    };
+
+    match command {
+        CacheCommand::List { provider } => {
+            let users = service.get_cached_users(provider.as_deref()).await.unwrap_or_else(|err| fail(err));
+            for user in &users {
+                let expiration = Utc.timestamp_millis_opt(user.expiration)
+                    .single()
+                    .map(|expiration| expiration.to_rfc3339())
+                    .unwrap_or_default();
+                let state = if user.expired { "expired" } else { "fresh" };
+                println!("{}\t{}\t{}\t{}\t{}", user.provider, user.username, user.id, expiration, state);
+            }
+            println!("{} cached user(s)", users.len());
+        },
+        CacheCommand::Show { provider, username } => {
+            let user = service.get_cached_user(provider, username).await.unwrap_or_else(|err| fail(err));
+            print_user(user, provider, username);
+        },
+        CacheCommand::Refresh { provider, username } => {
+            if !service.has_provider(provider) {
+                fail(AppError::Invalid(format!("Unknown provider: {}", provider)));
+            }
+            let user = service.force_refresh(provider, username).await.unwrap_or_else(|err| fail(err));
+            print_user(user, provider, username);
+        },
+        CacheCommand::Purge { provider: Some(provider), username: Some(username), .. } => {
+            if !service.purge_user(provider, username).await.unwrap_or_else(|err| fail(err)) {
+                not_found(provider, username);
+            }
+            println!("Purged {} user {}", provider, username);
+        },
+        CacheCommand::Purge { expired, .. } => {
+            let purged = service.purge_users(*expired).await.unwrap_or_else(|err| fail(err));
+            println!("Purged {} cached user(s)", purged);
+        },
+        CacheCommand::Vacuum => {
+            service.vacuum().await.unwrap_or_else(|err| fail(err));
+            println!("Vacuumed the database");
+        },
+    }
+}
@@ -1,2 +1,9 @@
+pub mod fediverse_actor_mapper;
+pub mod gitea_user_mapper;
+pub mod github_org_mapper;
+pub mod github_repo_mapper;
 pub mod github_user_mapper;
+pub mod gitlab_user_mapper;
+pub mod language_color_mapper;
+pub mod local_profile_mapper;
 pub mod pronouns_mapper;
\ No newline at end of file
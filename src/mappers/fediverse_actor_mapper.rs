@@ -0,0 +1,76 @@
+use crate::models::fediverse_actor::FediverseActor;
+use crate::models::profile::Profile;
+
+
+pub fn to_profile(model: &FediverseActor, provider: &str, handle: &str) -> Profile {
+    let model_clone = model.clone();
+    let avatar_url = match &model_clone.icon {
+        Some(serde_json::Value::Array(icons)) => icons.first().and_then(get_url),
+        Some(icon) => get_url(icon),
+        None => None
+    };
+    Profile {
+        provider: provider.to_string(),
+        id: to_id(handle),
+        login: handle.to_string(),
+        name: model_clone.name.as_deref()
+            .map(strip_html)
+            .filter(|name| !name.is_empty()),
+        location: get_field(&model_clone, "location"),
+        avatar_url: avatar_url.unwrap_or_default(),
+        pronouns: get_field(&model_clone, "pronouns"),
+        tagline: model_clone.summary.as_deref()
+            .map(strip_html)
+            .filter(|summary| !summary.is_empty()),
+        pinned: Vec::new(),
+        sponsors: None,
+    }
+}
+
+// Hash the validated user@instance handle into a stable id for the cache key. The id of the actor is not used,
+// since the instance chooses it and could claim the id of an actor on another instance.
+pub fn to_id(handle: &str) -> i64 {
+    let hash = handle.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    (hash >> 1) as i64
+}
+
+// Get the value of a profile metadata field by name, such as pronouns.
+fn get_field(model: &FediverseActor, name: &str) -> Option<String> {
+    model.attachment.iter()
+        .filter(|attachment| attachment.attachment_type == "PropertyValue")
+        .find(|attachment| attachment.name.as_deref().is_some_and(|field| field.trim().eq_ignore_ascii_case(name)))
+        .and_then(|attachment| attachment.value.as_deref())
+        .map(strip_html)
+        .filter(|value| !value.is_empty())
+}
+
+fn get_url(icon: &serde_json::Value) -> Option<String> {
+    icon.get("url")?.as_str().map(|url| url.to_string())
+}
+
+// Reduce the HTML of summaries and fields to a single line of plain text.
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for char in html.chars() {
+        match char {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            },
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(char),
+            _ => ()
+        }
+    }
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
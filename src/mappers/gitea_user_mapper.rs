@@ -0,0 +1,19 @@
+use crate::models::gitea_user::GiteaUser;
+use crate::models::profile::Profile;
+
+
+pub fn to_profile(model: &GiteaUser, provider: &str) -> Profile {
+    let model_clone = model.clone();
+    Profile {
+        provider: provider.to_string(),
+        id: model_clone.id,
+        login: model_clone.login,
+        name: model_clone.full_name.filter(|name| !name.is_empty()),
+        location: model_clone.location.filter(|location| !location.is_empty()),
+        avatar_url: model_clone.avatar_url,
+        pronouns: None,
+        tagline: None,
+        pinned: Vec::new(),
+        sponsors: None,
+    }
+}
@@ -0,0 +1,35 @@
+use crate::entities;
+use crate::models;
+
+
+pub fn to_entity(model: &models::github_org::GithubOrg) -> entities::github_org::GithubOrg {
+    let model_clone = model.clone();
+    entities::github_org::GithubOrg {
+        id: model_clone.id,
+        login: model_clone.login,
+        name: model_clone.name,
+        description: model_clone.description,
+        location: model_clone.location,
+        blog: model_clone.blog,
+        public_repos: model_clone.public_repos,
+        public_members: model_clone.public_members,
+        avatar_url: model_clone.avatar_url,
+        // Set expiration a day from now
+        expiration: chrono::prelude::Utc::now().timestamp_millis() + (1000 * 60 * 60 * 24),
+    }
+}
+
+pub fn to_model(entity: &entities::github_org::GithubOrg) -> models::github_org::GithubOrg {
+    let entity_clone = entity.clone();
+    models::github_org::GithubOrg {
+        id: entity_clone.id,
+        login: entity_clone.login,
+        name: entity_clone.name,
+        description: entity_clone.description,
+        location: entity_clone.location,
+        blog: entity_clone.blog,
+        public_repos: entity_clone.public_repos,
+        public_members: entity_clone.public_members,
+        avatar_url: entity_clone.avatar_url,
+    }
+}
@@ -0,0 +1,50 @@
+use crate::entities;
+use crate::models;
+use crate::models::github_repo::{GithubLicense, GithubRepoOwner};
+
+
+pub fn to_entity(model: &models::github_repo::GithubRepo) -> entities::github_repo::GithubRepo {
+    let model_clone = model.clone();
+    let (license_spdx_id, license_name) = match model_clone.license {
+        Some(license) => (license.spdx_id, Some(license.name)),
+        None => (None, None)
+    };
+    entities::github_repo::GithubRepo {
+        id: model_clone.id,
+        owner_id: model_clone.owner.id,
+        owner_login: model_clone.owner.login,
+        owner_avatar_url: model_clone.owner.avatar_url,
+        name: model_clone.name,
+        full_name: model_clone.full_name,
+        description: model_clone.description,
+        language: model_clone.language,
+        stars: model_clone.stargazers_count,
+        forks: model_clone.forks_count,
+        license_spdx_id,
+        license_name,
+        // Set expiration a day from now
+        expiration: chrono::prelude::Utc::now().timestamp_millis() + (1000 * 60 * 60 * 24),
+    }
+}
+
+pub fn to_model(entity: &entities::github_repo::GithubRepo) -> models::github_repo::GithubRepo {
+    let entity_clone = entity.clone();
+    models::github_repo::GithubRepo {
+        id: entity_clone.id,
+        name: entity_clone.name,
+        full_name: entity_clone.full_name,
+        description: entity_clone.description,
+        language: entity_clone.language,
+        stargazers_count: entity_clone.stars,
+        forks_count: entity_clone.forks,
+        license: entity_clone.license_name.map(|name| GithubLicense {
+            spdx_id: entity_clone.license_spdx_id,
+            name
+        }),
+        owner: GithubRepoOwner {
+            id: entity_clone.owner_id,
+            login: entity_clone.owner_login,
+            avatar_url: entity_clone.owner_avatar_url
+        }
+    }
+}
@@ -1,27 +1,117 @@
 use crate::entities;
 use crate::models;
+use crate::models::cache_validators::CacheValidators;
+use crate::models::cached_user::CachedUser;
+use crate::models::github_graphql::GithubGraphqlUser;
+use crate::models::profile::Profile;
 
 
-pub fn to_entity(model: &models::github_user::GithubUser) -> entities::github_user::GithubUser {
+pub fn to_entity(model: &Profile, validators: &CacheValidators) -> entities::github_user::GithubUser {
     let model_clone = model.clone();
+    let validators_clone = validators.clone();
     entities::github_user::GithubUser {
+        provider: model_clone.provider,
         id: model_clone.id,
         username: model_clone.login,
         avatar_url: model_clone.avatar_url,
         location: model_clone.location,
         name: model_clone.name,
-        // Set expiration a day from now
-        expiration: chrono::prelude::Utc::now().timestamp_millis() + (1000 * 60 * 60 * 24),
+        pronouns: model_clone.pronouns,
+        tagline: model_clone.tagline,
+        pinned: Some(model_clone.pinned.join("\n")).filter(|pinned| !pinned.is_empty()),
+        sponsors: model_clone.sponsors,
+        etag: validators_clone.etag,
+        last_modified: validators_clone.last_modified,
+        expiration: next_expiration(),
     }
 }
 
-pub fn to_model(entity: &entities::github_user::GithubUser) -> models::github_user::GithubUser {
+// Cached users expire a day from now
+pub fn next_expiration() -> i64 {
+    chrono::prelude::Utc::now().timestamp_millis() + (1000 * 60 * 60 * 24)
+}
+
+pub fn to_cached_user(entity: &entities::github_user::GithubUser, now: i64) -> CachedUser {
+    let entity_clone = entity.clone();
+    CachedUser {
+        provider: entity_clone.provider,
+        id: entity_clone.id,
+        username: entity_clone.username,
+        name: entity_clone.name,
+        location: entity_clone.location,
+        avatar_url: entity_clone.avatar_url,
+        pronouns: entity_clone.pronouns,
+        tagline: entity_clone.tagline,
+        pinned: to_pinned(entity_clone.pinned.as_deref()),
+        sponsors: entity_clone.sponsors,
+        etag: entity_clone.etag,
+        last_modified: entity_clone.last_modified,
+        expiration: entity_clone.expiration,
+        expired: entity_clone.expiration <= now,
+    }
+}
+
+pub fn to_validators(entity: &entities::github_user::GithubUser) -> CacheValidators {
+    CacheValidators {
+        etag: entity.etag.clone(),
+        last_modified: entity.last_modified.clone(),
+    }
+}
+
+pub fn to_model(entity: &entities::github_user::GithubUser) -> Profile {
     let entity_clone = entity.clone();
-    models::github_user::GithubUser {
+    Profile {
+        provider: entity_clone.provider,
         id: entity_clone.id,
         name: entity_clone.name,
         avatar_url: entity_clone.avatar_url,
         location: entity_clone.location,
-        login: entity_clone.username
+        login: entity_clone.username,
+        pronouns: entity_clone.pronouns,
+        tagline: entity_clone.tagline,
+        pinned: to_pinned(entity_clone.pinned.as_deref()),
+        sponsors: entity_clone.sponsors,
     }
-}
\ No newline at end of file
+}
+
+fn to_pinned(pinned: Option<&str>) -> Vec<String> {
+    pinned.map_or_else(Vec::new, |pinned| pinned.lines().map(|name| name.to_string()).collect())
+}
+
+pub fn to_profile(model: &models::github_user::GithubUser, provider: &str) -> Profile {
+    let model_clone = model.clone();
+    Profile {
+        provider: provider.to_string(),
+        id: model_clone.id,
+        login: model_clone.login,
+        name: model_clone.name,
+        location: model_clone.location,
+        avatar_url: model_clone.avatar_url,
+        pronouns: None,
+        tagline: None,
+        pinned: Vec::new(),
+        sponsors: None,
+    }
+}
+
+pub fn graphql_to_profile(model: &GithubGraphqlUser, provider: &str) -> Profile {
+    let model_clone = model.clone();
+    let non_empty = |value: Option<String>| value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    Profile {
+        provider: provider.to_string(),
+        id: model_clone.database_id,
+        login: model_clone.login,
+        name: model_clone.name,
+        location: model_clone.location,
+        avatar_url: model_clone.avatar_url,
+        pronouns: non_empty(model_clone.pronouns),
+        // The card fonts have no emoji, so only the message of the status is asked for
+        tagline: non_empty(model_clone.status.and_then(|status| status.message)),
+        pinned: model_clone.pinned_items
+            .map(|pinned| pinned.nodes.into_iter().filter_map(|item| item.name).collect())
+            .unwrap_or_default(),
+        sponsors: model_clone.sponsors.map(|sponsors| sponsors.total_count),
+    }
+}
@@ -0,0 +1,24 @@
+use crate::models::gitlab_user::GitlabUser;
+use crate::models::profile::Profile;
+
+
+pub fn to_profile(model: &GitlabUser, provider: &str, base_url: &str) -> Profile {
+    let model_clone = model.clone();
+    Profile {
+        provider: provider.to_string(),
+        id: model_clone.id,
+        login: model_clone.username,
+        name: model_clone.name,
+        location: model_clone.location.filter(|location| !location.is_empty()),
+        // Self-hosted instances may return avatars relative to the instance
+        avatar_url: match model_clone.avatar_url {
+            Some(url) if url.starts_with('/') => format!("{}{}", base_url, url),
+            Some(url) => url,
+            None => String::new()
+        },
+        pronouns: None,
+        tagline: None,
+        pinned: Vec::new(),
+        sponsors: None,
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+
+pub struct LanguageColorMapper {
+    hash_map: HashMap<String, [u8; 3]>,
+}
+
+impl LanguageColorMapper {
+    pub fn new() -> Self {
+        // Colours of the most common languages, as used by GitHub linguist
+        let colors: Vec<(&str, &str)> = vec![
+            ("c", "#555555"),
+            ("c#", "#178600"),
+            ("c++", "#f34b7d"),
+            ("clojure", "#db5855"),
+            ("css", "#563d7c"),
+            ("dart", "#00b4ab"),
+            ("dockerfile", "#384d54"),
+            ("elixir", "#6e4a7e"),
+            ("elm", "#60b5cc"),
+            ("erlang", "#b83998"),
+            ("f#", "#b845fc"),
+            ("go", "#00add8"),
+            ("haskell", "#5e5086"),
+            ("html", "#e34c26"),
+            ("java", "#b07219"),
+            ("javascript", "#f1e05a"),
+            ("julia", "#a270ba"),
+            ("jupyter notebook", "#da5b0b"),
+            ("kotlin", "#a97bff"),
+            ("lua", "#000080"),
+            ("nix", "#7e7eff"),
+            ("objective-c", "#438eff"),
+            ("ocaml", "#3be133"),
+            ("perl", "#0298c3"),
+            ("php", "#4f5d95"),
+            ("powershell", "#012456"),
+            ("python", "#3572a5"),
+            ("r", "#198ce7"),
+            ("ruby", "#701516"),
+            ("rust", "#dea584"),
+            ("scala", "#c22d40"),
+            ("scss", "#c6538c"),
+            ("shell", "#89e051"),
+            ("swift", "#f05138"),
+            ("typescript", "#3178c6"),
+            ("vue", "#41b883"),
+            ("zig", "#ec915c"),
+        ];
+
+        let mut hash_map: HashMap<String, [u8; 3]> = HashMap::new();
+        for (language, hex) in colors {
+            let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).unwrap_or(0);
+            hash_map.insert(language.to_string(), [channel(1), channel(3), channel(5)]);
+        }
+
+        LanguageColorMapper { hash_map }
+    }
+
+    // Get the RGB colour of a language, falling back to grey for unknown languages.
+    pub fn to_color(&self, language: &str) -> [u8; 3] {
+        self.hash_map
+            .get(&language.to_lowercase())
+            .cloned()
+            .unwrap_or([128, 128, 128])
+    }
+}
+
+impl Default for LanguageColorMapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,49 @@
+use crate::entities;
+use crate::models;
+use crate::models::profile::Profile;
+
+
+pub static LOCAL_PROVIDER: &str = "local";
+
+pub fn to_entity(model: &models::local_profile::LocalProfile) -> entities::local_profile::LocalProfile {
+    let model_clone = model.clone();
+    entities::local_profile::LocalProfile {
+        slug: model_clone.slug,
+        name: model_clone.name,
+        pronouns: model_clone.pronouns,
+        location: model_clone.location,
+        tagline: model_clone.tagline,
+        avatar: model_clone.avatar,
+        updated: model_clone.updated,
+    }
+}
+
+pub fn to_model(entity: &entities::local_profile::LocalProfile) -> models::local_profile::LocalProfile {
+    let entity_clone = entity.clone();
+    models::local_profile::LocalProfile {
+        slug: entity_clone.slug,
+        name: entity_clone.name,
+        pronouns: entity_clone.pronouns,
+        location: entity_clone.location,
+        tagline: entity_clone.tagline,
+        avatar: entity_clone.avatar,
+        updated: entity_clone.updated,
+    }
+}
+
+pub fn to_profile(model: &models::local_profile::LocalProfile) -> Profile {
+    let model_clone = model.clone();
+    Profile {
+        provider: LOCAL_PROVIDER.to_string(),
+        // Local profiles are keyed by slug and have no numeric id
+        id: 0,
+        login: model_clone.slug,
+        name: Some(model_clone.name),
+        location: model_clone.location,
+        avatar_url: String::new(),
+        pronouns: model_clone.pronouns,
+        tagline: model_clone.tagline,
+        pinned: Vec::new(),
+        sponsors: None,
+    }
+}
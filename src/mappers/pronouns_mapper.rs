@@ -51,4 +51,10 @@ impl PronounsMapper {
     pub fn to_pronouns_tag(&self, pronouns_query: &str) -> Option<String> {
         self.hash_map.get(pronouns_query).cloned()
     }
+}
+
+impl Default for PronounsMapper {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,282 @@
+use rusqlite::{Connection, TransactionBehavior};
+
+use crate::errors::AppError;
+use crate::{TABLE_AVATAR_CACHE, TABLE_GITHUB_ORG, TABLE_GITHUB_REPO, TABLE_GITHUB_USER, TABLE_LOCAL_PROFILE, TABLE_MISSING_USER};
+
+
+// A step of the schema. The version of the database is kept in PRAGMA user_version and is the version of the last step applied.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    apply: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+// Every step in order. Steps are never edited once released, a change of the schema is a new step at the end.
+// Databases created before migrations have a version of 0 and may already have some of the tables and columns,
+// and the first release of migrations created the whole schema at version 1, so every step keeps what is there.
+static MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "Create the GitHub user cache", apply: create_user_table },
+    Migration { version: 2, description: "Create the GitHub organization cache", apply: create_org_table },
+    Migration { version: 3, description: "Create the GitHub repository cache", apply: create_repo_table },
+    Migration { version: 4, description: "Key cached users by provider and id", apply: key_users_by_provider },
+    Migration { version: 5, description: "Create the local profile table", apply: create_local_profile_table },
+    Migration { version: 6, description: "Add pronouns and taglines to cached users", apply: add_user_pronouns },
+    Migration { version: 7, description: "Add revalidation headers to cached users", apply: add_user_validators },
+    Migration { version: 8, description: "Create the avatar cache", apply: create_avatar_cache_table },
+    Migration { version: 9, description: "Create the negative cache of missing users", apply: create_missing_user_table },
+    Migration { version: 10, description: "Add pinned repositories and sponsors to cached users", apply: add_user_highlights },
+];
+
+pub fn get_latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+pub fn get_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", (), |row| row.get(0))
+}
+
+// Get the steps not applied yet to the database, failing for a database migrated by a newer version
+pub fn get_pending(conn: &Connection) -> Result<Vec<&'static Migration>, AppError> {
+    let version = get_version(conn).map_err(|e| AppError::Storage(e.to_string()))?;
+    if version > get_latest_version() {
+        return Err(AppError::Storage(format!("Database is at version {} but the latest known version is {}", version, get_latest_version())));
+    }
+    Ok(MIGRATIONS.iter().filter(|migration| migration.version > version).collect())
+}
+
+// Apply the pending steps in a single transaction, so a failed step leaves the database as it was.
+// The version is read once the write lock is held, so another process migrating at the same time is waited for.
+pub fn run(conn: &mut Connection) -> Result<Vec<&'static Migration>, AppError> {
+    let transaction = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| AppError::Storage(e.to_string()))?;
+    let pending = get_pending(&transaction)?;
+    for migration in &pending {
+        log::info!("Migrating database to version {}: {}", migration.version, migration.description);
+        (migration.apply)(&transaction)
+            .and_then(|_| transaction.pragma_update(None, "user_version", migration.version))
+            .map_err(|e| AppError::Storage(format!("Migration to version {} failed: {}", migration.version, e)))?;
+    }
+    transaction.commit().map_err(|e| AppError::Storage(e.to_string()))?;
+    Ok(pending)
+}
+
+fn create_user_table(conn: &Connection) -> rusqlite::Result<()> {
+    let query = format!("CREATE TABLE IF NOT EXISTS {} (
+        id          INTEGER PRIMARY KEY,
+        username    TEXT NOT NULL COLLATE NOCASE,
+        name        TEXT,
+        location    TEXT,
+        avatar_url  TEXT NOT NULL,
+        expiration  INTEGER NOT NULL
+    )", TABLE_GITHUB_USER);
+    conn.execute(query.as_str(), ())?;
+    Ok(())
+}
+
+fn create_org_table(conn: &Connection) -> rusqlite::Result<()> {
+    let query = format!("CREATE TABLE IF NOT EXISTS {} (
+        id              INTEGER PRIMARY KEY,
+        login           TEXT NOT NULL COLLATE NOCASE,
+        name            TEXT,
+        description     TEXT,
+        location        TEXT,
+        blog            TEXT,
+        public_repos    INTEGER NOT NULL,
+        public_members  INTEGER NOT NULL,
+        avatar_url      TEXT NOT NULL,
+        expiration      INTEGER NOT NULL
+    )", TABLE_GITHUB_ORG);
+    conn.execute(query.as_str(), ())?;
+    Ok(())
+}
+
+fn create_repo_table(conn: &Connection) -> rusqlite::Result<()> {
+    let query = format!("CREATE TABLE IF NOT EXISTS {} (
+        id                  INTEGER PRIMARY KEY,
+        owner_id            INTEGER NOT NULL,
+        owner_login         TEXT NOT NULL,
+        owner_avatar_url    TEXT NOT NULL,
+        name                TEXT NOT NULL,
+        full_name           TEXT NOT NULL COLLATE NOCASE,
+        description         TEXT,
+        language            TEXT,
+        stars               INTEGER NOT NULL,
+        forks               INTEGER NOT NULL,
+        license_spdx_id     TEXT,
+        license_name        TEXT,
+        expiration          INTEGER NOT NULL
+    )", TABLE_GITHUB_REPO);
+    conn.execute(query.as_str(), ())?;
+    Ok(())
+}
+
+// SQLite can't change a primary key, so users are copied into a new table keyed by provider and id
+fn key_users_by_provider(conn: &Connection) -> rusqlite::Result<()> {
+    if has_column(conn, TABLE_GITHUB_USER, "provider")? {
+        return Ok(());
+    }
+    let query = format!("ALTER TABLE {table} RENAME TO {table}Legacy", table = TABLE_GITHUB_USER);
+    conn.execute(query.as_str(), ())?;
+    let query = format!("CREATE TABLE {} (
+        provider    TEXT NOT NULL,
+        id          INTEGER NOT NULL,
+        username    TEXT NOT NULL COLLATE NOCASE,
+        name        TEXT,
+        location    TEXT,
+        avatar_url  TEXT NOT NULL,
+        expiration  INTEGER NOT NULL,
+        PRIMARY KEY (provider, id)
+    )", TABLE_GITHUB_USER);
+    conn.execute(query.as_str(), ())?;
+    // Every user cached before then came from GitHub
+    let query = format!("INSERT INTO {table} (provider, id, username, name, location, avatar_url, expiration)
+        SELECT 'github', id, username, name, location, avatar_url, expiration FROM {table}Legacy", table = TABLE_GITHUB_USER);
+    conn.execute(query.as_str(), ())?;
+    let query = format!("DROP TABLE {}Legacy", TABLE_GITHUB_USER);
+    conn.execute(query.as_str(), ())?;
+    Ok(())
+}
+
+fn create_local_profile_table(conn: &Connection) -> rusqlite::Result<()> {
+    let query = format!("CREATE TABLE IF NOT EXISTS {} (
+        slug        TEXT PRIMARY KEY COLLATE NOCASE,
+        name        TEXT NOT NULL,
+        pronouns    TEXT,
+        location    TEXT,
+        tagline     TEXT,
+        avatar      BLOB,
+        updated     INTEGER NOT NULL
+    )", TABLE_LOCAL_PROFILE);
+    conn.execute(query.as_str(), ())?;
+    Ok(())
+}
+
+fn add_user_pronouns(conn: &Connection) -> rusqlite::Result<()> {
+    add_columns(conn, TABLE_GITHUB_USER, &["pronouns", "tagline"])
+}
+
+fn add_user_validators(conn: &Connection) -> rusqlite::Result<()> {
+    add_columns(conn, TABLE_GITHUB_USER, &["etag", "last_modified"])
+}
+
+fn add_user_highlights(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, TABLE_GITHUB_USER, "pinned", "TEXT")?;
+    add_column(conn, TABLE_GITHUB_USER, "sponsors", "INTEGER")
+}
+
+fn create_avatar_cache_table(conn: &Connection) -> rusqlite::Result<()> {
+    let query = format!("CREATE TABLE IF NOT EXISTS {} (
+        key         TEXT PRIMARY KEY,
+        data        BLOB NOT NULL,
+        size        INTEGER NOT NULL,
+        expiration  INTEGER NOT NULL,
+        last_used   INTEGER NOT NULL
+    )", TABLE_AVATAR_CACHE);
+    conn.execute(query.as_str(), ())?;
+    Ok(())
+}
+
+fn create_missing_user_table(conn: &Connection) -> rusqlite::Result<()> {
+    let query = format!("CREATE TABLE IF NOT EXISTS {} (
+        provider    TEXT NOT NULL,
+        username    TEXT NOT NULL COLLATE NOCASE,
+        expiration  INTEGER NOT NULL,
+        PRIMARY KEY (provider, username)
+    )", TABLE_MISSING_USER);
+    conn.execute(query.as_str(), ())?;
+    Ok(())
+}
+
+// Add nullable text columns the table doesn't have yet
+fn add_columns(conn: &Connection, table: &str, columns: &[&str]) -> rusqlite::Result<()> {
+    for column in columns {
+        add_column(conn, table, column, "TEXT")?;
+    }
+    Ok(())
+}
+
+// Add a nullable column of the given type unless the table has it already
+fn add_column(conn: &Connection, table: &str, column: &str, column_type: &str) -> rusqlite::Result<()> {
+    if !has_column(conn, table, column)? {
+        let query = format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, column_type);
+        conn.execute(query.as_str(), ())?;
+    }
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2", [table, column], |row| row.get::<_, i64>(0))
+        .map(|count| count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has_table(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |row| row.get::<_, i64>(0))
+            .map(|count| count > 0)
+    }
+
+    fn get_columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut statement = conn.prepare("SELECT name FROM pragma_table_info(?1)").unwrap();
+        let columns = statement.query_map([table], |row| row.get(0)).unwrap();
+        columns.map(|column| column.unwrap()).collect()
+    }
+
+    #[test]
+    fn migrates_a_new_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(run(&mut conn).unwrap().len(), MIGRATIONS.len());
+        assert_eq!(get_version(&conn).unwrap(), get_latest_version());
+        for table in [TABLE_GITHUB_USER, TABLE_GITHUB_ORG, TABLE_GITHUB_REPO, TABLE_LOCAL_PROFILE, TABLE_AVATAR_CACHE, TABLE_MISSING_USER] {
+            assert!(has_table(&conn, table).unwrap(), "{} is missing", table);
+        }
+        assert_eq!(get_columns(&conn, TABLE_GITHUB_USER), [
+            "provider", "id", "username", "name", "location", "avatar_url", "expiration", "pronouns", "tagline", "etag", "last_modified", "pinned", "sponsors"
+        ]);
+        assert!(run(&mut conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keeps_users_cached_before_migrations() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_user_table(&conn).unwrap();
+        let query = format!("INSERT INTO {} (id, username, avatar_url, expiration) VALUES (7, 'octo', 'https://example.com/7.png', 100)", TABLE_GITHUB_USER);
+        conn.execute(query.as_str(), ()).unwrap();
+
+        run(&mut conn).unwrap();
+        let query = format!("SELECT provider, id, username, pronouns FROM {}", TABLE_GITHUB_USER);
+        let user: (String, i64, String, Option<String>) = conn.query_row(query.as_str(), (), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))).unwrap();
+        assert_eq!(user, (String::from("github"), 7, String::from("octo"), None));
+        assert!(!has_table(&conn, &format!("{}Legacy", TABLE_GITHUB_USER)).unwrap());
+    }
+
+    #[test]
+    fn keeps_a_schema_that_is_already_there() {
+        // Databases that got the whole schema at version 1 run every later step over it
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        assert_eq!(run(&mut conn).unwrap().len(), MIGRATIONS.len() - 1);
+        assert_eq!(get_version(&conn).unwrap(), get_latest_version());
+    }
+
+    #[test]
+    fn refuses_a_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", get_latest_version() + 1).unwrap();
+        assert!(matches!(run(&mut conn), Err(AppError::Storage(_))));
+    }
+
+    #[test]
+    fn failed_step_leaves_the_database_as_it_was() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // A table of the same name with a different schema makes the copy of users fail
+        let query = format!("CREATE TABLE {}Legacy (id INTEGER)", TABLE_GITHUB_USER);
+        conn.execute(query.as_str(), ()).unwrap();
+        assert!(run(&mut conn).is_err());
+        assert_eq!(get_version(&conn).unwrap(), 0);
+        assert!(!has_table(&conn, TABLE_GITHUB_USER).unwrap());
+    }
+}
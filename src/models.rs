@@ -1,2 +1,18 @@
+pub mod avatar_cache_stats;
+pub mod cache_validators;
+pub mod cached;
+pub mod cached_user;
+pub mod circuit_status;
 pub mod empty;
-pub mod github_user;
\ No newline at end of file
+pub mod fediverse_actor;
+pub mod gitea_user;
+pub mod github_graphql;
+pub mod github_installation_token;
+pub mod github_org;
+pub mod github_repo;
+pub mod github_user;
+pub mod gitlab_user;
+pub mod health;
+pub mod local_profile;
+pub mod profile;
+pub mod refresh_status;
\ No newline at end of file
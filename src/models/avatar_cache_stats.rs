@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AvatarCacheStats {
+    pub entries: i64,
+    pub bytes: i64,
+    pub max_bytes: i64,
+}
@@ -0,0 +1,24 @@
+use reqwest::header::HeaderMap;
+
+// Validators of a cached response, sent back to ask whether it has changed.
+#[derive(Debug, Default, Clone)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    pub fn from_headers(header_map: &HeaderMap) -> CacheValidators {
+        let get_header = |key: &str| header_map.get(key)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        CacheValidators {
+            etag: get_header("etag"),
+            last_modified: get_header("last-modified"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
@@ -0,0 +1,7 @@
+// A value read from the cache, which may be served past its expiration.
+#[derive(Debug, Clone)]
+pub struct Cached<T> {
+    pub value: T,
+    // Seconds since the value expired, or None if it is fresh
+    pub stale_for: Option<i64>,
+}
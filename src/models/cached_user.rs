@@ -0,0 +1,21 @@
+use serde::Serialize;
+
+// A user stored in the profile cache, shown on the admin API and CLI.
+#[derive(Debug, Serialize, Clone)]
+pub struct CachedUser {
+    pub provider: String,
+    pub id: i64,
+    pub username: String,
+    pub name: Option<String>,
+    pub location: Option<String>,
+    pub avatar_url: String,
+    pub pronouns: Option<String>,
+    pub tagline: Option<String>,
+    pub pinned: Vec<String>,
+    pub sponsors: Option<i64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    // Milliseconds since the epoch
+    pub expiration: i64,
+    pub expired: bool,
+}
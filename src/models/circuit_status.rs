@@ -0,0 +1,11 @@
+use serde::Serialize;
+
+// State of the circuit breaker of a provider, shown on the health endpoint.
+#[derive(Debug, Serialize, Clone)]
+pub struct CircuitStatus {
+    // Either closed, open or half_open
+    pub state: String,
+    pub failures: u32,
+    // Timestamp in seconds a probe is let through at, while the circuit is open
+    pub retry_at: Option<i64>,
+}
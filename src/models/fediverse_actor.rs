@@ -0,0 +1,37 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebFinger {
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub links: Vec<WebFingerLink>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebFingerLink {
+    pub rel: String,
+    #[serde(rename = "type")]
+    pub link_type: Option<String>,
+    pub href: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FediverseActor {
+    pub id: String,
+    pub preferred_username: String,
+    pub name: Option<String>,
+    pub summary: Option<String>,
+    // Either a single image object or a list of them
+    pub icon: Option<serde_json::Value>,
+    #[serde(default)]
+    pub attachment: Vec<FediverseAttachment>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FediverseAttachment {
+    #[serde(rename = "type")]
+    pub attachment_type: String,
+    pub name: Option<String>,
+    pub value: Option<String>,
+}
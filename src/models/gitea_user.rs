@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GiteaUser {
+    pub id: i64,
+    pub login: String,
+    pub full_name: Option<String>,
+    pub location: Option<String>,
+    pub avatar_url: String,
+}
@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GraphqlResponse<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Vec<GraphqlError>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GraphqlError {
+    // Such as NOT_FOUND or RATE_LIMITED
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+    pub message: String,
+}
+
+// Point based limit of the GraphQL API, reported in the body of every query that asks for it
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphqlRateLimit {
+    pub cost: i64,
+    pub remaining: i64,
+    pub reset_at: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GithubUserQuery {
+    pub rate_limit: Option<GraphqlRateLimit>,
+    pub user: Option<GithubGraphqlUser>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GithubGraphqlUser {
+    pub database_id: i64,
+    pub login: String,
+    pub name: Option<String>,
+    pub location: Option<String>,
+    pub avatar_url: String,
+    pub pronouns: Option<String>,
+    pub status: Option<GithubUserStatus>,
+    pub pinned_items: Option<GraphqlNodes<GithubPinnedItem>>,
+    pub sponsors: Option<GraphqlCount>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubUserStatus {
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GraphqlNodes<T> {
+    pub nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphqlCount {
+    pub total_count: i64,
+}
+
+// Only repositories are asked for, gists come back as empty objects
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubPinnedItem {
+    pub name: Option<String>,
+}
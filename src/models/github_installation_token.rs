@@ -0,0 +1,7 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubInstallationToken {
+    pub token: String,
+    pub expires_at: String,
+}
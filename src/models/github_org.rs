@@ -0,0 +1,16 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubOrg {
+    pub id: i32,
+    pub login: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub blog: Option<String>,
+    pub public_repos: i64,
+    // Not part of the organisation response, counted from the public members list
+    #[serde(default)]
+    pub public_members: i64,
+    pub avatar_url: String,
+}
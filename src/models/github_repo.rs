@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubRepo {
+    pub id: i64,
+    pub name: String,
+    pub full_name: String,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub stargazers_count: i64,
+    pub forks_count: i64,
+    pub license: Option<GithubLicense>,
+    pub owner: GithubRepoOwner,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubRepoOwner {
+    pub id: i32,
+    pub login: String,
+    pub avatar_url: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubLicense {
+    pub spdx_id: Option<String>,
+    pub name: String,
+}
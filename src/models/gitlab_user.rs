@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GitlabUser {
+    pub id: i64,
+    pub username: String,
+    pub name: Option<String>,
+    // Only included when fetching a single user
+    #[serde(default)]
+    pub location: Option<String>,
+    pub avatar_url: Option<String>,
+}
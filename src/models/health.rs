@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+use super::circuit_status::CircuitStatus;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Health {
+    // Degraded while a provider is failing and cached profiles are served instead
+    pub status: String,
+    pub github: CircuitStatus,
+}
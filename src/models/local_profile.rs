@@ -0,0 +1,14 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LocalProfile {
+    pub slug: String,
+    pub name: String,
+    pub pronouns: Option<String>,
+    pub location: Option<String>,
+    pub tagline: Option<String>,
+    // Avatar image bytes are served through the card, not the API
+    #[serde(skip_serializing)]
+    pub avatar: Option<Vec<u8>>,
+    pub updated: i64,
+}
@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+// A profile from any provider, as cached and rendered on a card.
+#[derive(Debug, Serialize, Clone)]
+pub struct Profile {
+    pub provider: String,
+    pub id: i64,
+    pub login: String,
+    pub name: Option<String>,
+    pub location: Option<String>,
+    pub avatar_url: String,
+    pub pronouns: Option<String>,
+    pub tagline: Option<String>,
+    // Names of the pinned repositories, in the order they are pinned
+    pub pinned: Vec<String>,
+    pub sponsors: Option<i64>,
+}
@@ -0,0 +1,41 @@
+use serde::Serialize;
+
+// State of the background refresh scheduler, shown on the admin endpoint.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RefreshStatus {
+    pub enabled: bool,
+    // Seconds between runs
+    pub interval: u64,
+    pub top: usize,
+    // Seconds before expiration a profile is due for a refresh
+    pub ahead: i64,
+    // Requests left to foreground misses, the scheduler stops below it
+    pub reserve: i64,
+    pub last_run: Option<i64>,
+    pub runs: u64,
+    pub refreshed: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    // Decisions made about due profiles in the last run
+    pub last_decisions: Vec<RefreshDecision>,
+    // Most requested profiles, most requested first
+    pub tracked: Vec<TrackedUser>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RefreshDecision {
+    pub provider: String,
+    pub username: String,
+    pub requests: u64,
+    pub expiration: i64,
+    pub remaining: Option<i64>,
+    // Either refreshed, skipped or failed
+    pub action: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TrackedUser {
+    pub provider: String,
+    pub username: String,
+    pub requests: u64,
+}
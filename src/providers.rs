@@ -0,0 +1,11 @@
+pub mod avatar_reader;
+pub mod circuit_breaker;
+pub mod fediverse_provider;
+pub mod fixtures;
+pub mod gitea_provider;
+pub mod github_provider;
+pub mod gitlab_provider;
+pub mod profile_provider;
+pub mod public_address;
+pub mod rate_limit;
+pub mod retry;
\ No newline at end of file
@@ -0,0 +1,62 @@
+use image::ImageFormat;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::Response;
+
+use crate::errors::AppError;
+
+
+// Largest avatar downloaded from a provider, before it is resized
+pub const MAX_AVATAR_BYTES: usize = 2 * 1024 * 1024;
+// Formats accepted from providers, by Content-Type and by their magic bytes
+const ALLOWED_FORMATS: [(&str, ImageFormat); 4] = [
+    ("image/png", ImageFormat::Png),
+    ("image/jpeg", ImageFormat::Jpeg),
+    ("image/gif", ImageFormat::Gif),
+    ("image/webp", ImageFormat::WebP),
+];
+
+// Read the body of an avatar response, rejecting anything too large or not an allowed image format.
+// Rejected avatars are decode errors so the caller falls back to a generated avatar.
+pub async fn read_avatar(mut response: Response) -> Result<Vec<u8>, AppError> {
+    let content_type = response.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or("").trim().to_lowercase());
+    // Some servers don't label their images, the magic bytes are checked either way
+    if let Some(content_type) = content_type.filter(|content_type| content_type != "application/octet-stream") {
+        if !ALLOWED_FORMATS.iter().any(|(allowed, _)| *allowed == content_type) {
+            return Err(AppError::Decode(format!("Avatar has an unsupported content type: {}", content_type)));
+        }
+    }
+
+    let bytes = match read_body(&mut response, MAX_AVATAR_BYTES).await? {
+        Some(bytes) => bytes,
+        None => return Err(AppError::Decode(format!("Avatar is larger than {} bytes", MAX_AVATAR_BYTES)))
+    };
+
+    match image::guess_format(&bytes) {
+        Ok(format) if ALLOWED_FORMATS.iter().any(|(_, allowed)| *allowed == format) => Ok(bytes),
+        _ => Err(AppError::Decode(String::from("Avatar is not a supported image")))
+    }
+}
+
+// Read a body of at most the given size, None when it is larger
+pub async fn read_body(response: &mut Response, max_bytes: usize) -> Result<Option<Vec<u8>>, AppError> {
+    let content_length = response.headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|content_length| content_length > max_bytes) {
+        return Ok(None);
+    }
+
+    // The length may be missing or wrong, so the body is capped while it is read
+    let mut bytes = Vec::with_capacity(content_length.unwrap_or(0));
+    while let Some(chunk) = response.chunk().await? {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
@@ -0,0 +1,156 @@
+use std::sync::Mutex;
+
+use crate::errors::AppError;
+use crate::models::circuit_status::CircuitStatus;
+use crate::time;
+
+
+#[derive(Debug, Clone, Copy)]
+enum CircuitState {
+    Closed { failures: u32 },
+    // Requests fail fast until the timestamp in seconds
+    Open { until: i64 },
+    // A single probe is let through, which closes the circuit again if it succeeds.
+    // The timestamp in seconds it started at lets a new probe through if it never reports back.
+    HalfOpen { since: i64 },
+}
+
+// Stops calling a provider after repeated upstream failures, so requests fail fast
+// and cached profiles are served until the provider recovers.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: Mutex<CircuitState>,
+    // Consecutive failures that open the circuit
+    pub threshold: u32,
+    // Seconds the circuit stays open before a probe
+    pub cooldown: i64,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: i64) -> Self {
+        CircuitBreaker {
+            state: Mutex::new(CircuitState::Closed { failures: 0 }),
+            threshold: threshold.max(1),
+            cooldown,
+        }
+    }
+
+    // Fail fast while the circuit is open, letting one probe through once the cooldown is over.
+    // A probe whose request was dropped never records a result, so it is replaced after another cooldown.
+    pub fn check(&self, provider: &str) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        let now = time::get_timestamp();
+        match *state {
+            CircuitState::Closed { .. } => Ok(()),
+            CircuitState::Open { until } if now >= until => {
+                log::info!("Probing {} after the circuit was open", provider);
+                *state = CircuitState::HalfOpen { since: now };
+                Ok(())
+            },
+            CircuitState::Open { until } => Err(AppError::Unavailable { reset: until }),
+            CircuitState::HalfOpen { since } if now >= since + self.cooldown => {
+                log::warn!("Probing {} again, the last probe never finished", provider);
+                *state = CircuitState::HalfOpen { since: now };
+                Ok(())
+            },
+            // The probe is still in flight
+            CircuitState::HalfOpen { since } => Err(AppError::Unavailable { reset: since + self.cooldown }),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if matches!(*state, CircuitState::HalfOpen { .. }) {
+            log::info!("Closing the circuit, the probe succeeded");
+        }
+        *state = CircuitState::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self, provider: &str) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            CircuitState::Closed { failures } => failures + 1,
+            // A failed probe opens the circuit again right away
+            CircuitState::HalfOpen { .. } => self.threshold,
+            CircuitState::Open { .. } => return,
+        };
+        if failures >= self.threshold {
+            log::warn!("Opening the circuit for {} for {}s after {} failures", provider, self.cooldown, failures);
+            *state = CircuitState::Open { until: time::get_timestamp() + self.cooldown };
+        } else {
+            *state = CircuitState::Closed { failures };
+        }
+    }
+
+    pub fn get_status(&self) -> CircuitStatus {
+        match *self.state.lock().unwrap() {
+            CircuitState::Closed { failures } => CircuitStatus {
+                state: String::from("closed"),
+                failures,
+                retry_at: None,
+            },
+            CircuitState::Open { until } => CircuitStatus {
+                state: String::from("open"),
+                failures: self.threshold,
+                retry_at: Some(until),
+            },
+            CircuitState::HalfOpen { .. } => CircuitStatus {
+                state: String::from("half_open"),
+                failures: self.threshold,
+                retry_at: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_the_threshold() {
+        let breaker = CircuitBreaker::new(2, 60);
+        breaker.record_failure("test");
+        assert!(breaker.check("test").is_ok());
+        breaker.record_failure("test");
+        assert!(matches!(breaker.check("test"), Err(AppError::Unavailable { .. })));
+        assert_eq!(breaker.get_status().state, "open");
+    }
+
+    #[test]
+    fn success_resets_the_failures() {
+        let breaker = CircuitBreaker::new(2, 60);
+        breaker.record_failure("test");
+        breaker.record_success();
+        breaker.record_failure("test");
+        assert!(breaker.check("test").is_ok());
+        assert_eq!(breaker.get_status().failures, 1);
+    }
+
+    #[test]
+    fn lets_a_single_probe_through() {
+        let breaker = CircuitBreaker::new(1, 60);
+        *breaker.state.lock().unwrap() = CircuitState::Open { until: time::get_timestamp() - 1 };
+        assert!(breaker.check("test").is_ok());
+        assert!(breaker.check("test").is_err());
+        breaker.record_success();
+        assert!(breaker.check("test").is_ok());
+        assert_eq!(breaker.get_status().state, "closed");
+    }
+
+    #[test]
+    fn failed_probe_opens_again() {
+        let breaker = CircuitBreaker::new(3, 60);
+        *breaker.state.lock().unwrap() = CircuitState::HalfOpen { since: time::get_timestamp() };
+        breaker.record_failure("test");
+        assert_eq!(breaker.get_status().state, "open");
+    }
+
+    #[test]
+    fn replaces_a_probe_that_never_finished() {
+        let breaker = CircuitBreaker::new(1, 60);
+        *breaker.state.lock().unwrap() = CircuitState::HalfOpen { since: time::get_timestamp() - 60 };
+        assert!(breaker.check("test").is_ok());
+        assert!(breaker.check("test").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use async_trait::async_trait;
+use reqwest::{Client, Response, StatusCode, Url};
+use std::sync::Arc;
+use urlencoding::encode;
+
+use crate::errors::AppError;
+use crate::mappers::fediverse_actor_mapper;
+use crate::models::fediverse_actor::{FediverseActor, WebFinger};
+use crate::models::profile::Profile;
+use super::avatar_reader;
+use super::profile_provider::ProfileProvider;
+use super::public_address;
+
+
+// Resolves @user@instance handles through WebFinger and reads the ActivityPub actor.
+// Rate limits are per instance, so none are tracked here.
+pub struct FediverseProvider {
+    // Scheme used to reach instances, http only makes sense for a local stub server
+    pub scheme: String,
+    // Client that only connects to public addresses, unless private ones are allowed
+    pub client: Arc<Client>,
+    // Allow instances on loopback and private addresses, only for a local stub server
+    pub allow_private: bool,
+}
+
+impl FediverseProvider {
+    // Split a handle such as @user@instance into the user and instance.
+    pub fn parse_handle(handle: &str) -> Option<(&str, &str)> {
+        let (user, instance) = handle.trim_start_matches('@').split_once('@')?;
+        let is_user_valid = !user.is_empty()
+            && user.chars().all(|char| char.is_alphanumeric() || char == '_' || char == '.' || char == '-');
+        let is_instance_valid = !instance.is_empty()
+            && instance.chars().all(|char| char.is_ascii_alphanumeric() || char == '.' || char == '-' || char == ':');
+        if !is_user_valid || !is_instance_valid {
+            return None;
+        }
+        Some((user, instance))
+    }
+
+    // Check that an actor is served over the expected scheme by the instance of its handle, so an instance can't
+    // point at another host
+    fn is_on_instance(&self, url: &Url, instance: &str) -> bool {
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return false
+        };
+        url.scheme() == self.scheme && host.eq_ignore_ascii_case(instance)
+    }
+
+    async fn get(&self, url: &str, accept: &str) -> Result<Option<Response>, AppError> {
+        let parsed_url = Url::parse(url).map_err(|_| AppError::Upstream(format!("Invalid fediverse URL: {}", url)))?;
+        if !self.allow_private && !public_address::is_public_url(&parsed_url) {
+            return Err(AppError::Invalid(format!("Fediverse URL is not on a public address: {}", url)));
+        }
+        log::info!("Making request to {}...", url);
+        let response = self.client.get(url)
+            .header("Accept", accept)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND || response.status() == StatusCode::GONE {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            log::error!("{:?}", response.status());
+            return Err(AppError::Upstream(format!("Failed to get response from the fediverse, status: {}", response.status())));
+        }
+        Ok(Some(response))
+    }
+}
+
+#[async_trait]
+impl ProfileProvider for FediverseProvider {
+    fn name(&self) -> &str {
+        "fediverse"
+    }
+
+    async fn fetch_profile(&self, username: &str) -> Result<Option<Profile>, AppError> {
+        let (user, instance) = match FediverseProvider::parse_handle(username) {
+            Some(handle) => handle,
+            None => return Err(AppError::Invalid(format!("Invalid fediverse handle: {}", username)))
+        };
+        let handle = format!("{}@{}", user, instance).to_lowercase();
+
+        // Resolve the handle to the actor document
+        let url = format!("{}://{}/.well-known/webfinger?resource={}", self.scheme, instance, encode(&format!("acct:{}", handle)));
+        let webfinger: WebFinger = match self.get(&url, "application/jrd+json, application/json").await? {
+            Some(response) => serde_json::from_str(&response.text().await?)?,
+            None => return Ok(None)
+        };
+        let actor_url = webfinger.links.iter()
+            .filter(|link| link.rel == "self")
+            .find(|link| link.link_type.as_deref().is_some_and(|link_type| {
+                link_type.contains("activity+json") || link_type.contains("ld+json")
+            }))
+            .and_then(|link| link.href.clone());
+        let actor_url = match actor_url {
+            Some(actor_url) => actor_url,
+            None => return Err(AppError::NotFound(format!("No ActivityPub actor for {}!", handle)))
+        };
+        let is_actor_url_valid = Url::parse(&actor_url).is_ok_and(|url| self.is_on_instance(&url, instance));
+        if !is_actor_url_valid {
+            return Err(AppError::Upstream(format!("ActivityPub actor of {} is not on its instance: {}", handle, actor_url)));
+        }
+
+        let actor: FediverseActor = match self.get(&actor_url, "application/activity+json").await? {
+            Some(response) => serde_json::from_str(&response.text().await?)?,
+            None => return Ok(None)
+        };
+        Ok(Some(fediverse_actor_mapper::to_profile(&actor, self.name(), &handle)))
+    }
+
+    async fn fetch_avatar(&self, profile: &Profile) -> Result<Vec<u8>, AppError> {
+        if profile.avatar_url.is_empty() {
+            return Err(AppError::NotFound(String::from("Fediverse actor has no avatar!")));
+        }
+        let response = match self.get(&profile.avatar_url, "image/*").await? {
+            Some(response) => response,
+            None => return Err(AppError::NotFound(String::from("Fediverse avatar is gone!")))
+        };
+
+        avatar_reader::read_avatar(response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_handles() {
+        assert_eq!(FediverseProvider::parse_handle("@user@mastodon.social"), Some(("user", "mastodon.social")));
+        assert_eq!(FediverseProvider::parse_handle("user.name@example.org:8443"), Some(("user.name", "example.org:8443")));
+    }
+
+    #[test]
+    fn rejects_invalid_handles() {
+        for handle in ["@user", "@@example.org", "@user@", "@user@example.org/path", "@user@example.org?x=1", "@us/er@example.org", "@user@exa mple.org"] {
+            assert_eq!(FediverseProvider::parse_handle(handle), None, "{} should be rejected", handle);
+        }
+    }
+}
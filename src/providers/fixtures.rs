@@ -0,0 +1,206 @@
+use std::path::{Path, PathBuf};
+use axum::http;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING};
+use reqwest::{Response, StatusCode, Url};
+
+use crate::errors::AppError;
+use crate::providers::avatar_reader;
+
+
+// Extensions of the stored responses, by the content type they are served with
+const EXTENSIONS: [(&str, &str); 5] = [
+    ("json", "application/json"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+// Largest response recorded, responses are profiles and avatars so this is the size of the largest avatar
+const MAX_FIXTURE_BYTES: usize = avatar_reader::MAX_AVATAR_BYTES;
+
+// Responses stored in a directory by host and path, such as api.github.com/users/octocat.json
+// or avatars.githubusercontent.com/u/583231.png. Missing files are served as 404.
+#[derive(Debug, Clone)]
+pub struct FixtureStore {
+    pub dir: PathBuf,
+    // Send requests and store their responses, rather than serving the stored ones
+    pub record: bool,
+}
+
+impl FixtureStore {
+    pub fn new(dir: &str, record: bool) -> Self {
+        FixtureStore {
+            dir: PathBuf::from(dir),
+            record,
+        }
+    }
+
+    // Serve the stored response of a URL
+    pub async fn replay(&self, url: &Url) -> Result<Response, AppError> {
+        let base = self.get_base_path(url);
+        for (extension, content_type) in EXTENSIONS {
+            let path = FixtureStore::with_extension(&base, extension);
+            if !tokio::fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_file()) {
+                continue;
+            }
+            log::info!("Replaying {} from {}", url, path.display());
+            let body = tokio::fs::read(&path).await
+                .map_err(|e| AppError::Storage(format!("Failed to read fixture {}: {}", path.display(), e)))?;
+            return FixtureStore::to_response(StatusCode::OK, Some(content_type), body);
+        }
+
+        log::info!("No fixture for {}, expected at {}.*", url, base.display());
+        FixtureStore::to_response(StatusCode::NOT_FOUND, Some("application/json"), b"{}".to_vec())
+    }
+
+    // Store a live response, returning it rebuilt from the read body. Only successful responses are stored.
+    // The rebuilt response keeps the headers, which rate limits and revalidation are read from.
+    pub async fn record(&self, url: &Url, mut response: Response) -> Result<Response, AppError> {
+        let status = response.status();
+        let mut headers = response.headers().clone();
+        // The body has been read whole, so it is no longer framed the way it was sent
+        headers.remove(CONTENT_LENGTH);
+        headers.remove(TRANSFER_ENCODING);
+        let content_type = response.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or("").trim().to_lowercase());
+        let body = match avatar_reader::read_body(&mut response, MAX_FIXTURE_BYTES).await? {
+            Some(body) => body,
+            None => return Err(AppError::Upstream(format!("Response of {} is larger than {} bytes", url, MAX_FIXTURE_BYTES)))
+        };
+
+        let extension = content_type.as_deref()
+            .and_then(|content_type| EXTENSIONS.iter().find(|(_, known)| *known == content_type))
+            .map(|(extension, _)| *extension);
+        match extension {
+            Some(extension) if status.is_success() => {
+                let path = FixtureStore::with_extension(&self.get_base_path(url), extension);
+                FixtureStore::write(&path, &body).await
+                    .map_err(|e| AppError::Storage(format!("Failed to record fixture {}: {}", path.display(), e)))?;
+                log::info!("Recorded {} into {}", url, path.display());
+            },
+            _ => log::info!("Not recording {}, status: {}, content type: {:?}", url, status, content_type)
+        }
+
+        let mut response = FixtureStore::to_response(status, None, body)?;
+        *response.headers_mut() = headers;
+        Ok(response)
+    }
+
+    // Logins are case insensitive and the query only holds paging or cache busting, so both are left out
+    fn get_base_path(&self, url: &Url) -> PathBuf {
+        let mut path = self.dir.join(url.host_str().unwrap_or("localhost"));
+        for segment in url.path().to_lowercase().split('/').filter(|segment| !segment.is_empty() && *segment != "..") {
+            path.push(segment);
+        }
+        path
+    }
+
+    // Repository names may have dots of their own, so the extension is appended rather than replaced
+    fn with_extension(base: &Path, extension: &str) -> PathBuf {
+        let mut path = base.as_os_str().to_owned();
+        path.push(".");
+        path.push(extension);
+        PathBuf::from(path)
+    }
+
+    async fn write(path: &Path, body: &[u8]) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, body).await
+    }
+
+    fn to_response(status: StatusCode, content_type: Option<&str>, body: Vec<u8>) -> Result<Response, AppError> {
+        let mut builder = http::Response::builder().status(status);
+        if let Some(content_type) = content_type {
+            builder = builder.header(CONTENT_TYPE, content_type);
+        }
+        let response = builder.body(body)
+            .map_err(|e| AppError::Decode(e.to_string()))?;
+        Ok(Response::from(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_store(name: &str) -> FixtureStore {
+        let dir = std::env::temp_dir().join(format!("fixtures-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        FixtureStore::new(dir.to_str().unwrap(), true)
+    }
+
+    fn get_response(content_type: &str, body: Vec<u8>) -> Response {
+        FixtureStore::to_response(StatusCode::OK, Some(content_type), body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() {
+        let store = get_store("round-trip");
+        let url = Url::parse("https://api.github.com/users/Octocat?per_page=1").unwrap();
+        store.record(&url, get_response("application/json; charset=utf-8", b"{\"id\":1}".to_vec())).await.unwrap();
+
+        assert!(store.dir.join("api.github.com/users/octocat.json").is_file());
+        let response = store.replay(&Url::parse("https://api.github.com/users/octocat").unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(response.text().await.unwrap(), "{\"id\":1}");
+        std::fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_the_headers_of_recorded_responses() {
+        let store = get_store("headers");
+        let url = Url::parse("https://api.github.com/users/octocat").unwrap();
+        let mut response = FixtureStore::to_response(StatusCode::FORBIDDEN, Some("application/json"), b"{}".to_vec()).unwrap();
+        response.headers_mut().insert("x-ratelimit-remaining", "0".parse().unwrap());
+        response.headers_mut().insert("x-ratelimit-reset", "1700000000".parse().unwrap());
+        response.headers_mut().insert("etag", "\"abc\"".parse().unwrap());
+
+        let response = store.record(&url, response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()["x-ratelimit-remaining"], "0");
+        assert_eq!(response.headers()["x-ratelimit-reset"], "1700000000");
+        assert_eq!(response.headers()["etag"], "\"abc\"");
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    }
+
+    #[tokio::test]
+    async fn serves_missing_fixtures_as_not_found() {
+        let store = get_store("missing");
+        let response = store.replay(&Url::parse("https://api.github.com/users/ghost").unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn does_not_record_failures() {
+        let store = get_store("failure");
+        let url = Url::parse("https://api.github.com/users/ghost").unwrap();
+        let response = FixtureStore::to_response(StatusCode::NOT_FOUND, Some("application/json"), b"{}".to_vec()).unwrap();
+        let response = store.record(&url, response).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(!store.dir.exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_responses_that_are_too_large() {
+        let store = get_store("too-large");
+        let url = Url::parse("https://avatars.githubusercontent.com/u/1").unwrap();
+        let response = get_response("image/png", vec![0; MAX_FIXTURE_BYTES + 1]);
+        assert!(matches!(store.record(&url, response).await, Err(AppError::Upstream(_))));
+        assert!(!store.dir.exists());
+    }
+
+    #[test]
+    fn keeps_paths_inside_the_directory() {
+        let store = get_store("paths");
+        let path = store.get_base_path(&Url::parse("https://api.github.com/repos/a/b/../../../etc/passwd").unwrap());
+        assert!(path.starts_with(&store.dir));
+        let path = FixtureStore::with_extension(&store.get_base_path(&Url::parse("https://api.github.com/repos/a/site.io").unwrap()), "json");
+        assert!(path.ends_with("repos/a/site.io.json"));
+    }
+}
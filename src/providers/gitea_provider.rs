@@ -0,0 +1,70 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use std::sync::Arc;
+use urlencoding::encode;
+
+use crate::errors::AppError;
+use crate::mappers::gitea_user_mapper;
+use crate::models::{gitea_user::GiteaUser, profile::Profile};
+use super::avatar_reader;
+use super::profile_provider::ProfileProvider;
+use super::rate_limit::RateLimitState;
+
+
+// Gitea and its Forgejo fork share the same API, so one provider serves both under different names.
+pub struct GiteaProvider {
+    pub name: String,
+    pub base_url: String,
+    pub client: Arc<Client>,
+    pub rate_limit: RateLimitState,
+}
+
+#[async_trait]
+impl ProfileProvider for GiteaProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn remaining(&self) -> Option<i64> {
+        self.rate_limit.remaining()
+    }
+
+    async fn fetch_profile(&self, username: &str) -> Result<Option<Profile>, AppError> {
+        self.rate_limit.check(self.name())?;
+        let url = format!("{}/api/v1/users/{}", self.base_url, encode(username));
+        log::info!("Making request to {}...", url);
+        let response = self.client.get(url)
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+        // Instances only report a limit when one is configured
+        self.rate_limit.update(response.headers(), "x-ratelimit-remaining", "x-ratelimit-reset");
+
+        // Suspended and deleted accounts answer with a 410
+        if response.status() == StatusCode::NOT_FOUND || response.status() == StatusCode::GONE {
+            return Ok(None);
+        }
+        if let Some(err) = self.rate_limit.get_limited_error(response.status()) {
+            return Err(err);
+        }
+        if !response.status().is_success() {
+            log::error!("{:?}", response.status());
+            return Err(AppError::Upstream(format!("Failed to get response from {}, status: {}", self.name, response.status())));
+        }
+
+        let user: GiteaUser = serde_json::from_str(&response.text().await?)?;
+        Ok(Some(gitea_user_mapper::to_profile(&user, self.name())))
+    }
+
+    async fn fetch_avatar(&self, profile: &Profile) -> Result<Vec<u8>, AppError> {
+        let response = self.client.get(&profile.avatar_url)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            log::error!("{:?}", response.status());
+            return Err(AppError::Upstream(format!("Failed to get avatar from {}, status: {}", self.name, response.status())));
+        }
+
+        avatar_reader::read_avatar(response).await
+    }
+}
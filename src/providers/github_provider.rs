@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use std::sync::Arc;
+use urlencoding::encode;
+
+use crate::errors::AppError;
+use crate::mappers::github_user_mapper;
+use crate::models::cache_validators::CacheValidators;
+use crate::models::github_graphql::GithubUserQuery;
+use crate::models::{github_user::GithubUser, profile::Profile};
+use crate::services::github_api_service::GithubApiService;
+use super::avatar_reader;
+use super::profile_provider::{ProfileFetch, ProfileProvider};
+
+
+// Everything a card shows in a single query, along with the points it cost
+const USER_QUERY: &str = "query($login: String!) {
+    rateLimit { cost remaining resetAt }
+    user(login: $login) {
+        databaseId login name location avatarUrl pronouns
+        status { message }
+        pinnedItems(first: 3, types: REPOSITORY) { nodes { ... on Repository { name } } }
+        sponsors { totalCount }
+    }
+}";
+
+pub struct GithubProvider {
+    pub api: Arc<GithubApiService>,
+    pub image_client: Arc<Client>,
+    // Base URL of the avatars, which is /avatars on a GitHub Enterprise Server
+    pub avatars_url: String,
+    // Fetch profiles with the GraphQL API, which has fields the REST API lacks but needs authentication
+    pub graphql: bool,
+}
+
+impl GithubProvider {
+    pub async fn get_avatar_by_id(&self, id: i64) -> Result<Vec<u8>, AppError> {
+        let url = format!("{}/u/{}?v=4", self.avatars_url, id);
+        let response = self.api.send(|| self.image_client.get(&url)).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(AppError::NotFound(format!("No avatar for GitHub id {}", id)));
+        }
+        if !response.status().is_success() {
+            log::error!("{:?}", response.status());
+            return Err(AppError::Upstream(format!("Failed to get avatar from GitHub, status: {}", response.status())));
+        }
+
+        avatar_reader::read_avatar(response).await
+    }
+
+    async fn fetch_graphql_profile(&self, username: &str) -> Result<Option<Profile>, AppError> {
+        let query: GithubUserQuery = match self.api.query(USER_QUERY, serde_json::json!({ "login": username })).await {
+            Ok(query) => query,
+            Err(AppError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e)
+        };
+        if let Some(rate_limit) = &query.rate_limit {
+            self.api.update_graphql_rate_limit(rate_limit);
+        }
+
+        Ok(query.user.map(|user| github_user_mapper::graphql_to_profile(&user, self.name())))
+    }
+}
+
+#[async_trait]
+impl ProfileProvider for GithubProvider {
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    // Profiles are fetched against the limit of the API in use, avatars don't count towards either
+    fn remaining(&self) -> Option<i64> {
+        if self.graphql {
+            self.api.graphql_rate_limit.remaining()
+        } else {
+            self.api.rate_limit.remaining()
+        }
+    }
+
+    async fn fetch_profile(&self, username: &str) -> Result<Option<Profile>, AppError> {
+        if self.graphql {
+            return self.fetch_graphql_profile(username).await;
+        }
+        let url = format!("{}/users/{}", self.api.base_url, encode(username));
+        let response = match self.api.get(&url).await {
+            Ok(response) => response,
+            Err(AppError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e)
+        };
+
+        let contents = response.text().await?;
+        let user: GithubUser = serde_json::from_str(&contents)?;
+        Ok(Some(github_user_mapper::to_profile(&user, self.name())))
+    }
+
+    async fn fetch_profile_if_modified(&self, username: &str, validators: &CacheValidators) -> Result<ProfileFetch, AppError> {
+        // GraphQL has no conditional requests, a query costs a single point instead
+        if self.graphql {
+            return Ok(match self.fetch_graphql_profile(username).await? {
+                Some(profile) => ProfileFetch::Modified(Box::new(profile), CacheValidators::default()),
+                None => ProfileFetch::NotFound
+            });
+        }
+        let url = format!("{}/users/{}", self.api.base_url, encode(username));
+        let response = match self.api.get_if_modified(&url, validators).await {
+            Ok(Some(response)) => response,
+            Ok(None) => return Ok(ProfileFetch::NotModified),
+            Err(AppError::NotFound(_)) => return Ok(ProfileFetch::NotFound),
+            Err(e) => return Err(e)
+        };
+
+        let validators = CacheValidators::from_headers(response.headers());
+        let contents = response.text().await?;
+        let user: GithubUser = serde_json::from_str(&contents)?;
+        Ok(ProfileFetch::Modified(Box::new(github_user_mapper::to_profile(&user, self.name())), validators))
+    }
+
+    async fn fetch_avatar(&self, profile: &Profile) -> Result<Vec<u8>, AppError> {
+        self.get_avatar_by_id(profile.id).await
+    }
+}
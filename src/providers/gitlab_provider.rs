@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use reqwest::{Client, Response, StatusCode};
+use std::sync::Arc;
+use urlencoding::encode;
+
+use crate::errors::AppError;
+use crate::mappers::gitlab_user_mapper;
+use crate::models::{gitlab_user::GitlabUser, profile::Profile};
+use super::avatar_reader;
+use super::profile_provider::ProfileProvider;
+use super::rate_limit::RateLimitState;
+
+
+pub struct GitlabProvider {
+    pub base_url: String,
+    pub client: Arc<Client>,
+    pub rate_limit: RateLimitState,
+}
+
+impl GitlabProvider {
+    // Make a GET request, returning None when the resource does not exist.
+    async fn get(&self, url: &str) -> Result<Option<Response>, AppError> {
+        self.rate_limit.check(self.name())?;
+        log::info!("Making request to {}...", url);
+        let response = self.client.get(url)
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+        // GitLab reports its limit without the x- prefix
+        self.rate_limit.update(response.headers(), "ratelimit-remaining", "ratelimit-reset");
+
+        // Suspended and deleted accounts answer with a 410
+        if response.status() == StatusCode::NOT_FOUND || response.status() == StatusCode::GONE {
+            return Ok(None);
+        }
+        if let Some(err) = self.rate_limit.get_limited_error(response.status()) {
+            return Err(err);
+        }
+        if !response.status().is_success() {
+            log::error!("{:?}", response.status());
+            return Err(AppError::Upstream(format!("Failed to get response from GitLab, status: {}", response.status())));
+        }
+        Ok(Some(response))
+    }
+}
+
+#[async_trait]
+impl ProfileProvider for GitlabProvider {
+    fn name(&self) -> &str {
+        "gitlab"
+    }
+
+    fn remaining(&self) -> Option<i64> {
+        self.rate_limit.remaining()
+    }
+
+    async fn fetch_profile(&self, username: &str) -> Result<Option<Profile>, AppError> {
+        // Usernames are resolved through the user search, which omits the location
+        let url = format!("{}/api/v4/users?username={}", self.base_url, encode(username));
+        let users: Vec<GitlabUser> = match self.get(&url).await? {
+            Some(response) => serde_json::from_str(&response.text().await?)?,
+            None => return Ok(None)
+        };
+        let user = match users.into_iter().next() {
+            Some(user) => user,
+            None => return Ok(None)
+        };
+
+        let url = format!("{}/api/v4/users/{}", self.base_url, user.id);
+        let user: GitlabUser = match self.get(&url).await? {
+            Some(response) => serde_json::from_str(&response.text().await?)?,
+            None => user
+        };
+        Ok(Some(gitlab_user_mapper::to_profile(&user, self.name(), &self.base_url)))
+    }
+
+    async fn fetch_avatar(&self, profile: &Profile) -> Result<Vec<u8>, AppError> {
+        if profile.avatar_url.is_empty() {
+            return Err(AppError::NotFound(String::from("GitLab user has no avatar!")));
+        }
+        let response = self.client.get(&profile.avatar_url)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            log::error!("{:?}", response.status());
+            return Err(AppError::Upstream(format!("Failed to get avatar from GitLab, status: {}", response.status())));
+        }
+
+        avatar_reader::read_avatar(response).await
+    }
+}
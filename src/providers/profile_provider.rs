@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+
+use crate::errors::AppError;
+use crate::models::cache_validators::CacheValidators;
+use crate::models::profile::Profile;
+
+
+// Outcome of refreshing a cached profile.
+pub enum ProfileFetch {
+    Modified(Box<Profile>, CacheValidators),
+    NotModified,
+    NotFound,
+}
+
+// A forge that profiles can be fetched from, selected with the provider query parameter.
+#[async_trait]
+pub trait ProfileProvider: Send + Sync {
+    // Name of the provider, used in the query parameter and the cache key
+    fn name(&self) -> &str;
+
+    // Requests left in the current rate limit window, None if the provider has not reported one.
+    fn remaining(&self) -> Option<i64> {
+        None
+    }
+
+    // Fetch a profile by username, returning None if the user does not exist.
+    async fn fetch_profile(&self, username: &str) -> Result<Option<Profile>, AppError>;
+
+    // Fetch a profile unless it has not changed since the validators were stored.
+    // Providers without conditional requests always fetch the whole profile.
+    async fn fetch_profile_if_modified(&self, username: &str, _validators: &CacheValidators) -> Result<ProfileFetch, AppError> {
+        match self.fetch_profile(username).await? {
+            Some(profile) => Ok(ProfileFetch::Modified(Box::new(profile), CacheValidators::default())),
+            None => Ok(ProfileFetch::NotFound)
+        }
+    }
+
+    // Fetch the avatar image bytes of a profile.
+    async fn fetch_avatar(&self, profile: &Profile) -> Result<Vec<u8>, AppError>;
+}
@@ -0,0 +1,80 @@
+use std::sync::Mutex;
+use axum::http::HeaderMap;
+use reqwest::StatusCode;
+
+use crate::errors::AppError;
+use crate::time;
+
+
+#[derive(Debug, Default, Clone, Copy)]
+struct RateLimit {
+    // None until the provider reports a limit
+    remaining: Option<i64>,
+    reset: i64,
+    retry_after: i64,
+}
+
+// Seconds to back off when a provider limits us without saying for how long
+const DEFAULT_BACKOFF: i64 = 60;
+
+// Rate limit reported by a provider through its response headers.
+#[derive(Debug, Default)]
+pub struct RateLimitState {
+    state: Mutex<RateLimit>,
+}
+
+impl RateLimitState {
+    // Fail early if the provider told us to back off.
+    pub fn check(&self, provider: &str) -> Result<(), AppError> {
+        let state = *self.state.lock().unwrap();
+        let now = time::get_timestamp();
+        if state.retry_after > now {
+            log::warn!("Reached the secondary rate limit for {}!", provider);
+            return Err(AppError::RateLimited { reset: state.retry_after });
+        }
+        if state.remaining == Some(0) && state.reset > now {
+            log::warn!("Reached the request limit for {}!", provider);
+            return Err(AppError::RateLimited { reset: state.reset });
+        }
+        Ok(())
+    }
+
+    // Get the error for a response refused because of the rate limit, if it was.
+    // Call after update so the reset of the refusing response is known.
+    pub fn get_limited_error(&self, status: StatusCode) -> Option<AppError> {
+        let state = *self.state.lock().unwrap();
+        let is_limited = status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::FORBIDDEN && (state.remaining == Some(0) || state.retry_after > time::get_timestamp()));
+        if !is_limited {
+            return None;
+        }
+        let reset = state.retry_after.max(state.reset);
+        let reset = if reset > time::get_timestamp() { reset } else { time::get_timestamp() + DEFAULT_BACKOFF };
+        Some(AppError::RateLimited { reset })
+    }
+
+    // Requests left before the limit resets, None if unknown or already reset.
+    pub fn remaining(&self) -> Option<i64> {
+        let state = *self.state.lock().unwrap();
+        state.remaining.filter(|_| state.reset > time::get_timestamp())
+    }
+
+    // Record a limit reported some other way than by headers, such as in the body of a GraphQL response.
+    pub fn set(&self, remaining: i64, reset: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.remaining = Some(remaining);
+        state.reset = reset;
+    }
+
+    // Record the limit from the response headers, using the header names of the provider.
+    pub fn update(&self, header_map: &HeaderMap, remaining_key: &str, reset_key: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.remaining = get_int(header_map, remaining_key).or(state.remaining);
+        state.reset = get_int(header_map, reset_key).unwrap_or(state.reset);
+        state.retry_after = time::get_timestamp() + get_int(header_map, "retry-after").unwrap_or(0);
+    }
+}
+
+pub fn get_int(header_map: &HeaderMap, key: &str) -> Option<i64> {
+    header_map.get(key)?.to_str().ok()?.parse().ok()
+}
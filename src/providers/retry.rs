@@ -0,0 +1,79 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use reqwest::StatusCode;
+
+
+// Retries of idempotent requests with exponential backoff and full jitter, so clients
+// failing together don't retry together.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    // Total number of attempts, including the first one
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(attempts: u32) -> Self {
+        RetryPolicy {
+            attempts: attempts.max(1),
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+
+    // Delay before the given retry, picked at random up to the exponential backoff
+    pub fn get_delay(&self, retry: u32) -> Duration {
+        let backoff = self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let millis = backoff.as_millis() as u64;
+        Duration::from_millis(RetryPolicy::get_random() % (millis + 1))
+    }
+
+    pub fn is_retryable_status(status: StatusCode) -> bool {
+        matches!(status,
+            StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT)
+    }
+
+    // Connection failures and timeouts, but not errors of the request itself
+    pub fn is_retryable_error(err: &reqwest::Error) -> bool {
+        err.is_connect() || err.is_timeout()
+    }
+
+    // Randomly keyed hashers are enough for jitter without pulling in a random number generator
+    fn get_random() -> u64 {
+        RandomState::new().build_hasher().finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_stay_within_the_backoff() {
+        let policy = RetryPolicy::new(5);
+        for retry in 1..=6 {
+            let backoff = policy.base_delay.saturating_mul(2u32.pow(retry - 1)).min(policy.max_delay);
+            assert!(policy.get_delay(retry) <= backoff);
+        }
+    }
+
+    #[test]
+    fn makes_at_least_one_attempt() {
+        assert_eq!(RetryPolicy::new(0).attempts, 1);
+    }
+
+    #[test]
+    fn retries_only_server_failures() {
+        assert!(RetryPolicy::is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(RetryPolicy::is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!RetryPolicy::is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!RetryPolicy::is_retryable_status(StatusCode::NOT_IMPLEMENTED));
+    }
+}
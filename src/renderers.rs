@@ -0,0 +1,6 @@
+pub mod avatar_renderer;
+pub mod card_renderer;
+pub mod org_renderer;
+pub mod repo_renderer;
+pub mod team_renderer;
+pub mod theme;
\ No newline at end of file
@@ -0,0 +1,42 @@
+use std::error::Error;
+use image::{DynamicImage, Rgba, RgbaImage};
+use imageproc::rect::Rect;
+
+use super::card_renderer;
+
+
+// Number of cells on each side of an identicon
+const IDENTICON_CELLS: u32 = 5;
+
+// Render a symmetric identicon from a hash as PNG bytes.
+pub fn render_identicon(hash: &[u8], size: u32) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    if hash.len() < 18 {
+        return Err(String::from("Hash is too short for an identicon!").into());
+    }
+
+    // Keep the colour away from white so it stands out against the background
+    let color = Rgba([hash[15] / 4 * 3, hash[16] / 4 * 3, hash[17] / 4 * 3, 255]);
+    let mut canvas = RgbaImage::from_pixel(size, size, Rgba([240, 240, 240, 255]));
+    let margin = size / 12;
+    let cell_size = (size - 2 * margin) / IDENTICON_CELLS;
+    // Centre the grid when the size does not divide evenly
+    let offset = (size - cell_size * IDENTICON_CELLS) / 2;
+
+    // Only the left half and the middle column are derived from the hash, the right half mirrors it
+    let half = IDENTICON_CELLS.div_ceil(2);
+    for column in 0..half {
+        for row in 0..IDENTICON_CELLS {
+            let bit = (column * IDENTICON_CELLS + row) as usize;
+            if hash[bit / 8] >> (bit % 8) & 1 == 0 {
+                continue;
+            }
+            for x in [column, IDENTICON_CELLS - 1 - column] {
+                let rect = Rect::at((offset + x * cell_size) as i32, (offset + row * cell_size) as i32)
+                    .of_size(cell_size, cell_size);
+                imageproc::drawing::draw_filled_rect_mut(&mut canvas, rect, color);
+            }
+        }
+    }
+
+    card_renderer::encode_png(&DynamicImage::ImageRgba8(canvas))
+}
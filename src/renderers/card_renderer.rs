@@ -0,0 +1,311 @@
+use std::error::Error;
+use std::io::{BufWriter, Cursor};
+use image::imageops::FilterType;
+use image::io::{Limits, Reader};
+use image::{Rgba, ImageFormat, DynamicImage, GenericImageView, RgbaImage, ImageBuffer};
+use rusttype::{Scale, Font};
+
+use crate::models::profile::Profile;
+use super::theme::Theme;
+
+
+pub static REGULAR_FONT_DATA: &[u8] = include_bytes!("../../fonts/Oxygen-Regular.ttf");
+pub static LIGHT_FONT_DATA: &[u8] = include_bytes!("../../fonts/Oxygen-Light.ttf");
+pub static BOLD_FONT_DATA: &[u8] = include_bytes!("../../fonts/Oxygen-Bold.ttf");
+pub static LOCATION_ICON_DATA: &[u8] = include_bytes!("../../images/location.png");
+// Size of the rounded corners of the card templates, kept intact when stretching them
+const TEMPLATE_CORNER_SIZE: u32 = 16;
+// Largest width and height of an avatar the decoder accepts, which keeps decompression bombs out
+const MAX_IMAGE_DIMENSION: u32 = 4096;
+const MAX_IMAGE_ALLOC: u64 = 128 * 1024 * 1024;
+
+// Render a profile card as PNG bytes. This is CPU bound and meant to be run on the render pool.
+pub fn render_card(user: &Profile, pronouns_tag: &str, avatar: &[u8], theme: Theme) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let card_img = draw_card(user, pronouns_tag, avatar, theme)?;
+    encode_png(&card_img)
+}
+
+// Draw a profile card with the avatar overlaid.
+pub fn draw_card(user: &Profile, pronouns_tag: &str, avatar: &[u8], theme: Theme) -> Result<DynamicImage, Box<dyn Error + Send + Sync>> {
+    let avatar_img = draw_avatar(avatar, 100)?;
+    // Overlay avatar onto image
+    let mut card_img = draw_image(user, pronouns_tag, theme)?;
+    image::imageops::overlay(&mut card_img, &avatar_img, 20, 10);
+
+    Ok(card_img)
+}
+
+// Render a card telling a username does not exist as PNG bytes, drawn where the avatar and name would be.
+pub fn render_not_found(username: &str, theme: Theme) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    encode_png(&draw_placeholder("User not found", username, theme)?)
+}
+
+// Draw a card with a message in place of a profile, such as for a member of a team that could not be fetched.
+pub fn draw_placeholder(message: &str, username: &str, theme: Theme) -> Result<DynamicImage, Box<dyn Error + Send + Sync>> {
+    let mut img = load_template(theme)?;
+    let regular_font = Font::try_from_bytes(REGULAR_FONT_DATA).unwrap();
+    let light_font = Font::try_from_bytes(LIGHT_FONT_DATA).unwrap();
+    let left_margin = 140;
+
+    // Placeholder in place of the avatar
+    imageproc::drawing::draw_hollow_circle_mut(&mut img, (70, 60), 48, theme.secondary_color());
+    let question_scale = Scale { x: 56.0, y: 56.0 };
+    let question_width = imageproc::drawing::text_size(question_scale, &regular_font, "?").0;
+    imageproc::drawing::draw_text_mut(
+        &mut img,
+        theme.secondary_color(),
+        70 - question_width / 2,
+        32,
+        question_scale,
+        &regular_font,
+        "?"
+    );
+
+    imageproc::drawing::draw_text_mut(
+        &mut img,
+        theme.primary_color(),
+        left_margin,
+        20,
+        Scale { x: 24.0, y: 24.0 },
+        &regular_font,
+        message
+    );
+    let username_scale = Scale { x: 20.0, y: 20.0 };
+    let max_width = (img.width() as i32) - left_margin - 12;
+    imageproc::drawing::draw_text_mut(
+        &mut img,
+        theme.secondary_color(),
+        left_margin,
+        50,
+        username_scale,
+        &light_font,
+        &fit_text(&format!("@{}", username), username_scale, &light_font, max_width)
+    );
+
+    Ok(img)
+}
+
+// Decode an avatar and crop it into a circle of the given size.
+pub fn draw_avatar(avatar: &[u8], size: u32) -> Result<DynamicImage, Box<dyn Error + Send + Sync>> {
+    // Load avatar image
+    let avatar_img = decode_image(avatar)?;
+    // Border round the avatar
+    let mut canvas_avatar = avatar_img.to_rgba8();
+    round_image_mut(&mut canvas_avatar);
+    let avatar_img: DynamicImage = canvas_avatar.into();
+
+    Ok(avatar_img.resize(size, size, FilterType::Lanczos3))
+}
+
+// Crop an uploaded avatar into a square and store it at a fixed size as PNG bytes.
+pub fn normalize_avatar(avatar: &[u8], size: u32) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let avatar_img = decode_image(avatar)?;
+    encode_png(&avatar_img.resize_to_fill(size, size, FilterType::Lanczos3))
+}
+
+// Decode an image of any supported format within the dimension and allocation limits.
+pub fn decode_image(bytes: &[u8]) -> Result<DynamicImage, Box<dyn Error + Send + Sync>> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    limits.max_alloc = Some(MAX_IMAGE_ALLOC);
+
+    let mut reader = Reader::new(Cursor::new(bytes)).with_guessed_format()?;
+    reader.limits(limits);
+    Ok(reader.decode()?)
+}
+
+// Decode the card template of a theme.
+pub fn load_template(theme: Theme) -> Result<DynamicImage, Box<dyn Error + Send + Sync>> {
+    Ok(image::load_from_memory_with_format(theme.template_data(), ImageFormat::Png)?)
+}
+
+pub fn load_location_icon() -> Result<DynamicImage, Box<dyn Error + Send + Sync>> {
+    Ok(image::load_from_memory_with_format(LOCATION_ICON_DATA, ImageFormat::Png)?)
+}
+
+// Stretch the card template of a theme to any size, keeping its corners and border intact.
+// Sizes smaller than the two corners are drawn at the size of the corners.
+pub fn draw_background(theme: Theme, width: u32, height: u32) -> Result<DynamicImage, Box<dyn Error + Send + Sync>> {
+    let template = load_template(theme)?;
+    let (template_width, template_height) = template.dimensions();
+    let corner = TEMPLATE_CORNER_SIZE;
+    let width = width.max(2 * corner);
+    let height = height.max(2 * corner);
+    let mut canvas = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+
+    // Source and destination spans for the left/top, middle and right/bottom slices
+    let columns = [
+        (0, corner, 0, corner),
+        (corner, template_width.saturating_sub(2 * corner), corner, width - 2 * corner),
+        (template_width.saturating_sub(corner), corner, width - corner, corner),
+    ];
+    let rows = [
+        (0, corner, 0, corner),
+        (corner, template_height.saturating_sub(2 * corner), corner, height - 2 * corner),
+        (template_height.saturating_sub(corner), corner, height - corner, corner),
+    ];
+    for (src_x, src_width, dst_x, dst_width) in columns {
+        for (src_y, src_height, dst_y, dst_height) in rows {
+            // The middle slices are empty when the size is exactly the two corners
+            if src_width == 0 || src_height == 0 || dst_width == 0 || dst_height == 0 {
+                continue;
+            }
+            let slice = template
+                .crop_imm(src_x, src_y, src_width, src_height)
+                .resize_exact(dst_width, dst_height, FilterType::Nearest);
+            image::imageops::overlay(&mut canvas, &slice, i64::from(dst_x), i64::from(dst_y));
+        }
+    }
+
+    Ok(canvas)
+}
+
+// Shorten text with an ellipsis until it fits within the given width.
+pub fn fit_text(text: &str, scale: Scale, font: &Font, max_width: i32) -> String {
+    if imageproc::drawing::text_size(scale, font, text).0 <= max_width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate = format!("{}...", chars.iter().collect::<String>().trim_end());
+        if imageproc::drawing::text_size(scale, font, &candidate).0 <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+// Serialize an image as PNG bytes.
+pub fn encode_png(img: &DynamicImage) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut buffer = BufWriter::new(Cursor::new(Vec::new()));
+    img.write_to(&mut buffer, ImageFormat::Png)?;
+    let bytes: Vec<u8> = buffer.into_inner()?.into_inner();
+
+    Ok(bytes)
+}
+
+// Format a count the way GitHub does, such as 1.2k.
+pub fn format_count(count: i64) -> String {
+    match count {
+        count if count >= 1_000_000 => format!("{:.1}m", count as f64 / 1_000_000.0),
+        count if count >= 1_000 => format!("{:.1}k", count as f64 / 1_000.0),
+        count => count.to_string()
+    }
+}
+
+fn round_image_mut(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>) {
+    let dim = image.dimensions();
+    let mut canvas_mask = RgbaImage::new(dim.0, dim.1);
+    let width = dim.0 as f32;
+    let height = dim.1 as f32;
+    let midpoint = ((width/2.0) as i32, (height/2.0) as i32);
+    let radius = (width as i32) - midpoint.0;
+    imageproc::drawing::draw_filled_circle_mut(
+        &mut canvas_mask, 
+        midpoint,
+        radius,
+        Rgba([255,255,255,255])
+    );
+    // Apply clip mask
+    for (avatar_p, mask_p) in image.pixels_mut().zip(canvas_mask.pixels_mut()) {
+        if mask_p.0 != [255,255,255,255] {
+            avatar_p.0 = [0,0,0,0];
+        }
+    }
+}
+
+// Sponsors and pinned repositories in a single line, such as "12 sponsors · Pinned: hello-world, Spoon-Knife"
+fn get_highlights(user: &Profile) -> Option<String> {
+    let mut parts = Vec::new();
+    match user.sponsors {
+        Some(1) => parts.push(String::from("1 sponsor")),
+        Some(sponsors) if sponsors > 1 => parts.push(format!("{} sponsors", sponsors)),
+        _ => ()
+    }
+    if !user.pinned.is_empty() {
+        parts.push(format!("Pinned: {}", user.pinned.join(", ")));
+    }
+    Some(parts.join(" · ")).filter(|highlights| !highlights.is_empty())
+}
+
+fn draw_image(user: &Profile, pronouns_tag: &str, theme: Theme) -> Result<DynamicImage, Box<dyn Error + Send + Sync>> {
+    // Create profile card image
+    let mut img = load_template(theme)?;
+    let mut location_img = load_location_icon()?;
+    let regular_font = Font::try_from_bytes(REGULAR_FONT_DATA).unwrap();
+    let light_font = Font::try_from_bytes(LIGHT_FONT_DATA).unwrap();
+    let big_font_size = 24.0;
+    let smol_font_size = 20.0;
+    let tagline = user.tagline.clone().filter(|tagline| !tagline.is_empty());
+    let highlights = get_highlights(user);
+    
+    // Draw the person's name
+    {
+        let left_margin = 140;
+        let user = user.clone();
+        imageproc::drawing::draw_text_mut(
+            &mut img, 
+            theme.primary_color(),
+            left_margin, 
+            20, 
+            Scale { x: big_font_size, y: big_font_size },
+            &regular_font, 
+            &user.name.unwrap_or(user.login.to_string())
+        );
+        // Draw the person's location
+        let location = &user.location.unwrap_or("".to_string());
+        imageproc::drawing::draw_text_mut(
+            &mut img, 
+            theme.secondary_color(),
+            left_margin + 20, 
+            50, 
+            Scale {
+                x: smol_font_size,
+                y: smol_font_size
+            }, 
+            &light_font,
+            location
+        );
+        // Overlay location icon
+        if !location.is_empty() {
+            let buffer = theme.tint_icon(&mut location_img);
+            image::imageops::overlay(&mut img, &buffer, i64::from(left_margin), 55);
+        }
+        // Draw the person's pronouns
+        imageproc::drawing::draw_text_mut(
+            &mut img, 
+            theme.secondary_color(),
+            left_margin, 
+            78, 
+            Scale {
+                x: smol_font_size,
+                y: smol_font_size
+            }, 
+            &light_font, 
+            pronouns_tag
+        );
+        // Draw the person's tagline and highlights under the pronouns, sharing a line when there is room for one only
+        let first_y = if pronouns_tag.is_empty() { 80 } else { 99 };
+        let lines: Vec<String> = match (tagline, highlights) {
+            (Some(tagline), Some(highlights)) if !pronouns_tag.is_empty() => vec![format!("{} · {}", tagline, highlights)],
+            (tagline, highlights) => tagline.into_iter().chain(highlights).collect()
+        };
+        let line_scale = Scale { x: 15.0, y: 15.0 };
+        let max_width = (img.width() as i32) - left_margin - 12;
+        for (line, line_y) in lines.iter().zip((first_y..=99).step_by(19)) {
+            imageproc::drawing::draw_text_mut(
+                &mut img,
+                theme.secondary_color(),
+                left_margin,
+                line_y,
+                line_scale,
+                &light_font,
+                &fit_text(line, line_scale, &light_font, max_width)
+            );
+        }
+    };   
+
+    Ok(img)
+}
@@ -0,0 +1,73 @@
+use std::error::Error;
+use rusttype::{Scale, Font};
+
+use crate::models::github_org::GithubOrg;
+use super::card_renderer;
+use super::theme::Theme;
+
+
+const CARD_WIDTH: u32 = 400;
+const CARD_HEIGHT: u32 = 150;
+
+// Render an organisation card as PNG bytes.
+pub fn render_org(org: &GithubOrg, avatar: &[u8], theme: Theme) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut img = card_renderer::draw_background(theme, CARD_WIDTH, CARD_HEIGHT)?;
+    let mut location_img = card_renderer::load_location_icon()?;
+    let regular_font = Font::try_from_bytes(card_renderer::REGULAR_FONT_DATA).unwrap();
+    let light_font = Font::try_from_bytes(card_renderer::LIGHT_FONT_DATA).unwrap();
+    let big_scale = Scale { x: 24.0, y: 24.0 };
+    let smol_scale = Scale { x: 18.0, y: 18.0 };
+    let left_margin = 140;
+    let max_width = (CARD_WIDTH as i32) - left_margin - 16;
+
+    let logo_img = card_renderer::draw_avatar(avatar, 100)?;
+    image::imageops::overlay(&mut img, &logo_img, 20, 25);
+
+    // Draw the organisation's name
+    let name = org.name.clone()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(org.login.to_string());
+    imageproc::drawing::draw_text_mut(
+        &mut img,
+        theme.primary_color(),
+        left_margin,
+        14,
+        big_scale,
+        &regular_font,
+        &card_renderer::fit_text(&name, big_scale, &regular_font, max_width)
+    );
+
+    // Draw each available detail on its own line
+    let mut y = 44;
+    if let Some(description) = org.description.as_ref().filter(|text| !text.is_empty()) {
+        draw_detail(&mut img, theme, left_margin, y, &card_renderer::fit_text(description, smol_scale, &light_font, max_width), &light_font);
+        y += 22;
+    }
+    if let Some(location) = org.location.as_ref().filter(|text| !text.is_empty()) {
+        let buffer = theme.tint_icon(&mut location_img);
+        image::imageops::overlay(&mut img, &buffer, i64::from(left_margin), i64::from(y + 3));
+        draw_detail(&mut img, theme, left_margin + 20, y, &card_renderer::fit_text(location, smol_scale, &light_font, max_width - 20), &light_font);
+        y += 22;
+    }
+    if let Some(blog) = org.blog.as_ref().filter(|text| !text.is_empty()) {
+        draw_detail(&mut img, theme, left_margin, y, &card_renderer::fit_text(blog, smol_scale, &light_font, max_width), &light_font);
+    }
+
+    // Draw the counts along the bottom
+    let counts = format!("{} repos · {} members", org.public_repos, org.public_members);
+    draw_detail(&mut img, theme, left_margin, (CARD_HEIGHT as i32) - 32, &counts, &regular_font);
+
+    card_renderer::encode_png(&img)
+}
+
+fn draw_detail(img: &mut image::DynamicImage, theme: Theme, x: i32, y: i32, text: &str, font: &Font) {
+    imageproc::drawing::draw_text_mut(
+        img,
+        theme.secondary_color(),
+        x,
+        y,
+        Scale { x: 18.0, y: 18.0 },
+        font,
+        text
+    );
+}
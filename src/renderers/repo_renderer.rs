@@ -0,0 +1,97 @@
+use std::error::Error;
+use image::Rgba;
+use rusttype::{Scale, Font};
+
+use crate::models::github_repo::GithubRepo;
+use super::card_renderer;
+use super::theme::Theme;
+
+
+const CARD_WIDTH: u32 = 400;
+const CARD_HEIGHT: u32 = 150;
+
+// Render a repository card as PNG bytes, with the owner's avatar and a dot in the colour of the language.
+pub fn render_repo(repo: &GithubRepo, avatar: &[u8], language_color: [u8; 3], theme: Theme) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut img = card_renderer::draw_background(theme, CARD_WIDTH, CARD_HEIGHT)?;
+    let regular_font = Font::try_from_bytes(card_renderer::REGULAR_FONT_DATA).unwrap();
+    let light_font = Font::try_from_bytes(card_renderer::LIGHT_FONT_DATA).unwrap();
+    let big_scale = Scale { x: 22.0, y: 22.0 };
+    let smol_scale = Scale { x: 18.0, y: 18.0 };
+    let left_margin = 140;
+    let max_width = (CARD_WIDTH as i32) - left_margin - 16;
+
+    let avatar_img = card_renderer::draw_avatar(avatar, 100)?;
+    image::imageops::overlay(&mut img, &avatar_img, 20, 25);
+
+    // Draw the repository's full name
+    imageproc::drawing::draw_text_mut(
+        &mut img,
+        theme.primary_color(),
+        left_margin,
+        14,
+        big_scale,
+        &regular_font,
+        &card_renderer::fit_text(&repo.full_name, big_scale, &regular_font, max_width)
+    );
+
+    // Draw the description over up to two lines
+    if let Some(description) = repo.description.as_ref().filter(|text| !text.is_empty()) {
+        let (first_line, rest) = split_line(description, smol_scale, &light_font, max_width);
+        let lines = [first_line, card_renderer::fit_text(rest.trim(), smol_scale, &light_font, max_width)];
+        for (index, line) in lines.iter().enumerate() {
+            imageproc::drawing::draw_text_mut(
+                &mut img,
+                theme.secondary_color(),
+                left_margin,
+                44 + (index as i32) * 20,
+                smol_scale,
+                &light_font,
+                line
+            );
+        }
+    }
+
+    // Draw the language with its colour dot
+    let bottom = (CARD_HEIGHT as i32) - 32;
+    let mut x = left_margin;
+    if let Some(language) = &repo.language {
+        let [r, g, b] = language_color;
+        imageproc::drawing::draw_filled_circle_mut(&mut img, (x + 6, bottom + 10), 6, Rgba([r, g, b, 255]));
+        x += 18;
+        imageproc::drawing::draw_text_mut(&mut img, theme.secondary_color(), x, bottom, smol_scale, &regular_font, language);
+        x += imageproc::drawing::text_size(smol_scale, &regular_font, language).0 + 12;
+    }
+
+    // Draw the counts and licence after the language
+    let mut details = vec![
+        format!("{} stars", card_renderer::format_count(repo.stargazers_count)),
+        format!("{} forks", card_renderer::format_count(repo.forks_count)),
+    ];
+    if let Some(license) = &repo.license {
+        // GitHub reports unrecognised licences as NOASSERTION
+        let license_id = license.spdx_id.clone()
+            .filter(|spdx_id| spdx_id != "NOASSERTION")
+            .unwrap_or(license.name.clone());
+        details.push(license_id);
+    }
+    let details = card_renderer::fit_text(&details.join(" · "), smol_scale, &regular_font, (CARD_WIDTH as i32) - x - 16);
+    imageproc::drawing::draw_text_mut(&mut img, theme.secondary_color(), x, bottom, smol_scale, &regular_font, &details);
+
+    card_renderer::encode_png(&img)
+}
+
+// Split text at the last word that fits within the given width.
+fn split_line<'a>(text: &'a str, scale: Scale, font: &Font, max_width: i32) -> (String, &'a str) {
+    let mut end = 0;
+    for (index, _) in text.match_indices(' ').chain([(text.len(), "")]) {
+        if imageproc::drawing::text_size(scale, font, &text[..index]).0 > max_width {
+            break;
+        }
+        end = index;
+    }
+    if end == 0 {
+        // A single long word, let it be shortened instead
+        return (card_renderer::fit_text(text, scale, font, max_width), "");
+    }
+    (text[..end].to_string(), &text[end..])
+}
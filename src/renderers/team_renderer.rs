@@ -0,0 +1,75 @@
+use std::error::Error;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use rusttype::{Scale, Font};
+
+use crate::models::profile::Profile;
+use super::card_renderer;
+use super::theme::Theme;
+
+
+pub enum TeamMember {
+    Found(Box<Profile>, Vec<u8>),
+    // Username of a member that does not exist
+    Missing(String),
+    // Username of a member that could not be fetched
+    Failed(String),
+}
+
+pub struct TeamLayout {
+    pub columns: u32,
+    pub gap: u32,
+    pub title: Option<String>,
+    pub theme: Theme,
+}
+
+// Height reserved above the grid for the title header
+const TITLE_HEIGHT: u32 = 40;
+
+// Render the cards of a team into a single grid image as PNG bytes, with placeholders for missing members.
+pub fn render_team(members: &[TeamMember], layout: &TeamLayout) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let cards = members.iter()
+        .map(|member| match member {
+            TeamMember::Found(user, avatar) => card_renderer::draw_card(user, "", avatar, layout.theme),
+            TeamMember::Missing(username) => card_renderer::draw_placeholder("User not found", username, layout.theme),
+            TeamMember::Failed(username) => card_renderer::draw_placeholder("Profile unavailable", username, layout.theme),
+        })
+        .collect::<Result<Vec<DynamicImage>, _>>()?;
+    if cards.is_empty() {
+        return Err(String::from("No cards to render!").into());
+    }
+
+    // Every card shares the template size
+    let (card_width, card_height) = cards[0].dimensions();
+    let columns = layout.columns.clamp(1, cards.len() as u32);
+    let rows = (cards.len() as u32).div_ceil(columns);
+    let header_height = match layout.title {
+        Some(_) => TITLE_HEIGHT,
+        None => 0
+    };
+    let width = columns * card_width + (columns + 1) * layout.gap;
+    let height = header_height + rows * card_height + (rows + 1) * layout.gap;
+
+    let mut canvas = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+    if let Some(title) = &layout.title {
+        let bold_font = Font::try_from_bytes(card_renderer::BOLD_FONT_DATA).unwrap();
+        imageproc::drawing::draw_text_mut(
+            &mut canvas,
+            layout.theme.primary_color(),
+            layout.gap as i32,
+            (layout.gap + 4) as i32,
+            Scale { x: 28.0, y: 28.0 },
+            &bold_font,
+            title
+        );
+    }
+
+    for (index, card) in cards.iter().enumerate() {
+        let column = index as u32 % columns;
+        let row = index as u32 / columns;
+        let x = layout.gap + column * (card_width + layout.gap);
+        let y = header_height + layout.gap + row * (card_height + layout.gap);
+        image::imageops::overlay(&mut canvas, card, i64::from(x), i64::from(y));
+    }
+
+    card_renderer::encode_png(&canvas)
+}
@@ -0,0 +1,48 @@
+use image::Rgba;
+use serde::Deserialize;
+
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    // Card template embedded in the binary, so rendering doesn't depend on the working directory
+    pub fn template_data(&self) -> &'static [u8] {
+        match self {
+            Theme::Dark => include_bytes!("../../images/dark_template.png"),
+            Theme::Light => include_bytes!("../../images/white_template.png"),
+        }
+    }
+
+    // Colour of the name and headings
+    pub fn primary_color(&self) -> Rgba<u8> {
+        match self {
+            Theme::Dark => Rgba([255u8, 255u8, 255u8, 255u8]),
+            Theme::Light => Rgba([33u8, 37u8, 41u8, 255u8]),
+        }
+    }
+
+    // Colour of the location, pronouns and other details
+    pub fn secondary_color(&self) -> Rgba<u8> {
+        match self {
+            Theme::Dark => Rgba([192u8, 192u8, 192u8, 255u8]),
+            Theme::Light => Rgba([96u8, 96u8, 96u8, 255u8]),
+        }
+    }
+
+    // Adjust a black icon so it matches the secondary colour of the theme
+    pub fn tint_icon(&self, icon: &mut image::DynamicImage) -> image::DynamicImage {
+        match self {
+            Theme::Dark => {
+                image::imageops::invert(icon);
+                image::imageops::brighten(icon, -25).into()
+            },
+            Theme::Light => image::imageops::brighten(icon, 96).into(),
+        }
+    }
+}
@@ -1 +1,8 @@
-pub mod github_user_repository;
\ No newline at end of file
+pub mod avatar_cache_repository;
+pub mod github_org_repository;
+pub mod github_repo_repository;
+pub mod github_user_repository;
+pub mod local_profile_repository;
+pub mod memory_user_repository;
+pub mod missing_user_repository;
+pub mod user_repository;
\ No newline at end of file
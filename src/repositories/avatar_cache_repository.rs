@@ -0,0 +1,92 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::database::Database;
+use crate::{entities::avatar_cache::AvatarCache, TABLE_AVATAR_CACHE};
+
+
+#[derive(Clone)]
+pub struct AvatarCacheRepository {
+    pub db: Database,
+}
+
+impl AvatarCacheRepository {
+    // Get an avatar that has not expired
+    pub async fn get_by_key(&self, key: &str, now: i64) -> Result<Option<AvatarCache>, tokio_rusqlite::Error> {
+        let key_clone = key.to_string();
+        self.db.read(move |conn| {
+            let query = format!("SELECT key, data, size, expiration, last_used FROM {} WHERE key = ?1 AND expiration > ?2", TABLE_AVATAR_CACHE);
+            conn.query_row(query.as_str(), params![key_clone, now], |row| {
+                Ok(AvatarCache {
+                    key: row.get(0)?,
+                    data: row.get(1)?,
+                    size: row.get(2)?,
+                    expiration: row.get(3)?,
+                    last_used: row.get(4)?
+                })
+            }).optional()
+        }).await
+    }
+
+    // Mark an avatar as used, which keeps it from being evicted first
+    pub async fn update_last_used(&self, key: &str, now: i64) -> Result<(), tokio_rusqlite::Error> {
+        let key_clone = key.to_string();
+        self.db.write(move |conn| {
+            let query = format!("UPDATE {} SET last_used = ?2 WHERE key = ?1", TABLE_AVATAR_CACHE);
+            conn.execute(query.as_str(), params![key_clone, now])?;
+            Ok(())
+        }).await
+    }
+
+    pub async fn upsert(&self, entity: AvatarCache) -> Result<(), tokio_rusqlite::Error> {
+        self.db.write(move |conn| {
+            let query = format!("INSERT INTO {} (key, data, size, expiration, last_used)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT(key) DO UPDATE SET
+                    data = excluded.data,
+                    size = excluded.size,
+                    expiration = excluded.expiration,
+                    last_used = excluded.last_used", TABLE_AVATAR_CACHE);
+            conn.execute(query.as_str(), params![
+                entity.key,
+                entity.data,
+                entity.size,
+                entity.expiration,
+                entity.last_used
+                ]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    // Delete expired avatars, then the least recently used ones until the total size fits
+    pub async fn evict(&self, max_bytes: i64, now: i64) -> Result<usize, tokio_rusqlite::Error> {
+        self.db.write(move |conn| {
+            let query = format!("DELETE FROM {} WHERE expiration <= ?1", TABLE_AVATAR_CACHE);
+            let expired = conn.execute(query.as_str(), params![now])?;
+            let query = format!("DELETE FROM {table} WHERE key IN (
+                SELECT key FROM (
+                    SELECT key, SUM(size) OVER (ORDER BY last_used DESC, key) AS total FROM {table}
+                ) WHERE total > ?1
+            )", table = TABLE_AVATAR_CACHE);
+            let evicted = conn.execute(query.as_str(), params![max_bytes])?;
+
+            Ok(expired + evicted)
+        }).await
+    }
+
+    // Get the number of avatars and their total size
+    pub async fn get_size(&self) -> Result<(i64, i64), tokio_rusqlite::Error> {
+        self.db.read(move |conn| {
+            let query = format!("SELECT COUNT(*), COALESCE(SUM(size), 0) FROM {}", TABLE_AVATAR_CACHE);
+            conn.query_row(query.as_str(), [], |row| Ok((row.get(0)?, row.get(1)?)))
+        }).await
+    }
+
+    pub async fn delete_all(&self) -> Result<usize, tokio_rusqlite::Error> {
+        self.db.write(move |conn| {
+            let query = format!("DELETE FROM {}", TABLE_AVATAR_CACHE);
+            conn.execute(query.as_str(), [])
+        }).await
+    }
+}
@@ -0,0 +1,65 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::database::Database;
+use crate::{entities::github_org::GithubOrg, TABLE_GITHUB_ORG};
+
+
+pub struct GithubOrgRepository {
+    pub db: Database,
+}
+
+impl GithubOrgRepository {
+    pub async fn get_by_login(&self, login: &str) -> Result<Option<GithubOrg>, tokio_rusqlite::Error> {
+        let login_clone = login.to_string();
+        self.db.read(move |conn| {
+            let query = format!("SELECT id, login, name, description, location, blog, public_repos, public_members, avatar_url, expiration
+                FROM {} WHERE login = ?1", TABLE_GITHUB_ORG);
+            conn.query_row(query.as_str(), params![login_clone], |row| {
+                Ok(GithubOrg {
+                    id: row.get(0)?,
+                    login: row.get(1)?,
+                    name: row.get(2)?,
+                    description: row.get(3)?,
+                    location: row.get(4)?,
+                    blog: row.get(5)?,
+                    public_repos: row.get(6)?,
+                    public_members: row.get(7)?,
+                    avatar_url: row.get(8)?,
+                    expiration: row.get(9)?
+                })
+            }).optional()
+        }).await
+    }
+
+    pub async fn upsert(&self, entity: GithubOrg) -> Result<(), tokio_rusqlite::Error> {
+        self.db.write(move |conn| {
+            let query = format!("INSERT INTO {} (id, login, name, description, location, blog, public_repos, public_members, avatar_url, expiration)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                ON CONFLICT(id) DO UPDATE SET
+                    login = excluded.login,
+                    name = excluded.name,
+                    description = excluded.description,
+                    location = excluded.location,
+                    blog = excluded.blog,
+                    public_repos = excluded.public_repos,
+                    public_members = excluded.public_members,
+                    avatar_url = excluded.avatar_url,
+                    expiration = excluded.expiration", TABLE_GITHUB_ORG);
+            conn.execute(query.as_str(), params![
+                entity.id,
+                entity.login,
+                entity.name,
+                entity.description,
+                entity.location,
+                entity.blog,
+                entity.public_repos,
+                entity.public_members,
+                entity.avatar_url,
+                entity.expiration
+                ]
+            )?;
+
+            Ok(())
+        }).await
+    }
+}
@@ -0,0 +1,76 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::database::Database;
+use crate::{entities::github_repo::GithubRepo, TABLE_GITHUB_REPO};
+
+
+pub struct GithubRepoRepository {
+    pub db: Database,
+}
+
+impl GithubRepoRepository {
+    pub async fn get_by_full_name(&self, full_name: &str) -> Result<Option<GithubRepo>, tokio_rusqlite::Error> {
+        let full_name_clone = full_name.to_string();
+        self.db.read(move |conn| {
+            let query = format!("SELECT id, owner_id, owner_login, owner_avatar_url, name, full_name, description, language,
+                stars, forks, license_spdx_id, license_name, expiration
+                FROM {} WHERE full_name = ?1", TABLE_GITHUB_REPO);
+            conn.query_row(query.as_str(), params![full_name_clone], |row| {
+                Ok(GithubRepo {
+                    id: row.get(0)?,
+                    owner_id: row.get(1)?,
+                    owner_login: row.get(2)?,
+                    owner_avatar_url: row.get(3)?,
+                    name: row.get(4)?,
+                    full_name: row.get(5)?,
+                    description: row.get(6)?,
+                    language: row.get(7)?,
+                    stars: row.get(8)?,
+                    forks: row.get(9)?,
+                    license_spdx_id: row.get(10)?,
+                    license_name: row.get(11)?,
+                    expiration: row.get(12)?
+                })
+            }).optional()
+        }).await
+    }
+
+    pub async fn upsert(&self, entity: GithubRepo) -> Result<(), tokio_rusqlite::Error> {
+        self.db.write(move |conn| {
+            let query = format!("INSERT INTO {} (id, owner_id, owner_login, owner_avatar_url, name, full_name, description, language,
+                stars, forks, license_spdx_id, license_name, expiration)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                ON CONFLICT(id) DO UPDATE SET
+                    owner_id = excluded.owner_id,
+                    owner_login = excluded.owner_login,
+                    owner_avatar_url = excluded.owner_avatar_url,
+                    name = excluded.name,
+                    full_name = excluded.full_name,
+                    description = excluded.description,
+                    language = excluded.language,
+                    stars = excluded.stars,
+                    forks = excluded.forks,
+                    license_spdx_id = excluded.license_spdx_id,
+                    license_name = excluded.license_name,
+                    expiration = excluded.expiration", TABLE_GITHUB_REPO);
+            conn.execute(query.as_str(), params![
+                entity.id,
+                entity.owner_id,
+                entity.owner_login,
+                entity.owner_avatar_url,
+                entity.name,
+                entity.full_name,
+                entity.description,
+                entity.language,
+                entity.stars,
+                entity.forks,
+                entity.license_spdx_id,
+                entity.license_name,
+                entity.expiration
+                ]
+            )?;
+
+            Ok(())
+        }).await
+    }
+}
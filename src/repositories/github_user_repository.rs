@@ -1,107 +1,153 @@
-use rusqlite::params;
-use tokio_rusqlite::Connection;
+use async_trait::async_trait;
+use rusqlite::{params, OptionalExtension};
 
+use crate::database::Database;
 use crate::{entities::github_user::GithubUser, TABLE_GITHUB_USER};
+use crate::errors::AppError;
+use super::user_repository::UserRepository;
 
 
 pub struct GithubUserRepository {
-    pub conn: Connection,
+    pub db: Database,
 }
 
 impl GithubUserRepository {
-    pub async fn get_by_username(&self, username: &str) -> Option<GithubUser> {
-        let username_clone = username.to_string();
-        self.conn.call(move |conn| {
-            let query = format!("SELECT * FROM {} WHERE username = '{}'", TABLE_GITHUB_USER, username_clone);
-            let mut stmt = conn.prepare(query.as_str()).unwrap();
-            let users = stmt.query_map([], |row| {
-                Ok(crate::entities::github_user::GithubUser {
-                    id: row.get(0)?,
-                    username: row.get(1)?,
-                    name: row.get(2)?,
-                    location: row.get(3)?,
-                    avatar_url: row.get(4)?,
-                    expiration: row.get(5)?
-                })
-            })?.collect::<Result<Vec<crate::entities::github_user::GithubUser>, rusqlite::Error>>()?;
-    
-            Ok::<_, rusqlite::Error>(users)
-        }).await.unwrap().first().cloned()
+    fn to_entity(row: &rusqlite::Row) -> Result<GithubUser, rusqlite::Error> {
+        Ok(GithubUser {
+            provider: row.get(0)?,
+            id: row.get(1)?,
+            username: row.get(2)?,
+            name: row.get(3)?,
+            location: row.get(4)?,
+            avatar_url: row.get(5)?,
+            pronouns: row.get(6)?,
+            tagline: row.get(7)?,
+            etag: row.get(8)?,
+            last_modified: row.get(9)?,
+            expiration: row.get(10)?,
+            pinned: row.get(11)?,
+            sponsors: row.get(12)?
+        })
     }
+}
 
-    pub async fn get_by_id(&self, id: i32) -> Option<GithubUser> {
-        self.conn.call(move |conn| {
-            let query = format!("SELECT * FROM {} WHERE id = {}", TABLE_GITHUB_USER, id);
-            let mut stmt = conn.prepare(query.as_str()).unwrap();
-            let users = stmt.query_map([], |row| {
-                Ok(crate::entities::github_user::GithubUser {
-                    id: row.get(0)?,
-                    username: row.get(1)?,
-                    name: row.get(2)?,
-                    location: row.get(3)?,
-                    avatar_url: row.get(4)?,
-                    expiration: row.get(5)?
-                })
-            })?.collect::<Result<Vec<crate::entities::github_user::GithubUser>, rusqlite::Error>>()?;
-
-            Ok::<_, rusqlite::Error>(users)
-        }).await.unwrap().first().cloned()
+#[async_trait]
+impl UserRepository for GithubUserRepository {
+    async fn get_by_username(&self, provider: &str, username: &str) -> Result<Option<GithubUser>, AppError> {
+        let provider_clone = provider.to_string();
+        let username_clone = username.to_string();
+        Ok(self.db.read(move |conn| {
+            let query = format!("SELECT provider, id, username, name, location, avatar_url, pronouns, tagline, etag, last_modified, expiration, pinned, sponsors FROM {} WHERE provider = ?1 AND username = ?2", TABLE_GITHUB_USER);
+            conn.query_row(query.as_str(), params![provider_clone, username_clone], GithubUserRepository::to_entity).optional()
+        }).await?)
     }
 
-    pub async fn upsert(&self, entity: GithubUser) -> Result<(), tokio_rusqlite::Error> {
-        let insert_result = self.insert(entity.clone()).await;
-        match insert_result {
-            Ok(_) => Ok(()),
-            Err(_) => self.update(entity.clone()).await
-        }
+    async fn get_by_id(&self, provider: &str, id: i64) -> Result<Option<GithubUser>, AppError> {
+        let provider_clone = provider.to_string();
+        Ok(self.db.read(move |conn| {
+            let query = format!("SELECT provider, id, username, name, location, avatar_url, pronouns, tagline, etag, last_modified, expiration, pinned, sponsors FROM {} WHERE provider = ?1 AND id = ?2", TABLE_GITHUB_USER);
+            conn.query_row(query.as_str(), params![provider_clone, id], GithubUserRepository::to_entity).optional()
+        }).await?)
     }
 
-    pub async fn update(&self, entity: GithubUser) -> Result<(), tokio_rusqlite::Error> {
-        let entity_clone = entity.clone();
-        self.conn.call(move |conn| {
-            let query = format!("UPDATE {}
-                SET id = ?1,
-                SET username = ?2,
-                SET name = ?3,
-                SET location = ?4,
-                SET avatar_url = ?5,
-                SET expiration = ?6,
-                WHERE id = ?1", TABLE_GITHUB_USER);
-            let execute_result = conn.execute(query.as_str(), params![
-                entity_clone.id,
-                entity_clone.username,
-                entity_clone.name,
-                entity_clone.location,
-                entity_clone.avatar_url,
-                entity_clone.expiration
+    async fn upsert(&self, entity: GithubUser) -> Result<(), AppError> {
+        Ok(self.db.write(move |conn| {
+            let query = format!("INSERT INTO {} (provider, id, username, name, location, avatar_url, pronouns, tagline, etag, last_modified, expiration, pinned, sponsors)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                ON CONFLICT(provider, id) DO UPDATE SET
+                    username = excluded.username,
+                    name = excluded.name,
+                    location = excluded.location,
+                    avatar_url = excluded.avatar_url,
+                    pronouns = excluded.pronouns,
+                    tagline = excluded.tagline,
+                    etag = excluded.etag,
+                    last_modified = excluded.last_modified,
+                    expiration = excluded.expiration,
+                    pinned = excluded.pinned,
+                    sponsors = excluded.sponsors", TABLE_GITHUB_USER);
+            conn.execute(query.as_str(), params![
+                entity.provider,
+                entity.id,
+                entity.username,
+                entity.name,
+                entity.location,
+                entity.avatar_url,
+                entity.pronouns,
+                entity.tagline,
+                entity.etag,
+                entity.last_modified,
+                entity.expiration,
+                entity.pinned,
+                entity.sponsors
                 ]
-            );
+            )?;
 
-            match execute_result {
-                Ok(_) => Ok(()),
-                Err(e) => Err(e)
-            }
-        }).await
+            Ok(())
+        }).await?)
     }
 
-    pub async fn insert(&self, entity: GithubUser) -> Result<(), tokio_rusqlite::Error> {
-        let entity_clone = entity.clone();
-        self.conn.call(move |conn| {
-            let query = format!("INSERT INTO {} (id, username, name, location, avatar_url, expiration) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", TABLE_GITHUB_USER);
-            let execute_result = conn.execute(query.as_str(), params![
-                entity_clone.id, 
-                entity_clone.username, 
-                entity_clone.name, 
-                entity_clone.location, 
-                entity_clone.avatar_url,
-                entity_clone.expiration
-                ]
-            );
+    async fn update_expiration(&self, provider: &str, id: i64, expiration: i64) -> Result<(), AppError> {
+        let provider_clone = provider.to_string();
+        Ok(self.db.write(move |conn| {
+            let query = format!("UPDATE {} SET expiration = ?3 WHERE provider = ?1 AND id = ?2", TABLE_GITHUB_USER);
+            conn.execute(query.as_str(), params![provider_clone, id, expiration])?;
+            Ok(())
+        }).await?)
+    }
+
+    async fn get_all(&self, provider: Option<&str>) -> Result<Vec<GithubUser>, AppError> {
+        let provider_clone = provider.map(|provider| provider.to_string());
+        Ok(self.db.read(move |conn| {
+            let query = format!("SELECT provider, id, username, name, location, avatar_url, pronouns, tagline, etag, last_modified, expiration, pinned, sponsors FROM {} WHERE ?1 IS NULL OR provider = ?1 ORDER BY provider, username", TABLE_GITHUB_USER);
+            let mut statement = conn.prepare(query.as_str())?;
+            let users = statement.query_map(params![provider_clone], GithubUserRepository::to_entity)?
+                .collect::<Result<Vec<GithubUser>, _>>()?;
+            Ok(users)
+        }).await?)
+    }
+
+    async fn delete_by_username(&self, provider: &str, username: &str) -> Result<bool, AppError> {
+        let provider_clone = provider.to_string();
+        let username_clone = username.to_string();
+        Ok(self.db.write(move |conn| {
+            let query = format!("DELETE FROM {} WHERE provider = ?1 AND username = ?2", TABLE_GITHUB_USER);
+            Ok(conn.execute(query.as_str(), params![provider_clone, username_clone])? > 0)
+        }).await?)
+    }
 
-            match execute_result {
-                Ok(_) => Ok(()),
-                Err(e) => Err(e)
-            }
-        }).await
+    async fn delete_all(&self) -> Result<usize, AppError> {
+        Ok(self.db.write(move |conn| {
+            let query = format!("DELETE FROM {}", TABLE_GITHUB_USER);
+            conn.execute(query.as_str(), [])
+        }).await?)
     }
-}
\ No newline at end of file
+
+    async fn delete_expired(&self, now: i64) -> Result<usize, AppError> {
+        Ok(self.db.write(move |conn| {
+            let query = format!("DELETE FROM {} WHERE expiration <= ?1", TABLE_GITHUB_USER);
+            conn.execute(query.as_str(), params![now])
+        }).await?)
+    }
+
+    async fn vacuum(&self) -> Result<(), AppError> {
+        Ok(self.db.write(move |conn| conn.execute_batch("VACUUM")).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::database::MEMORY_PATH;
+    use crate::migrations;
+    use crate::repositories::user_repository::tests::check_round_trip;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let db = Database::open(MEMORY_PATH, 1, Duration::from_secs(1)).await.unwrap();
+        db.write(|conn| Ok(migrations::run(conn))).await.unwrap().unwrap();
+        check_round_trip(&GithubUserRepository { db }).await;
+    }
+}
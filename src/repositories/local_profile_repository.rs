@@ -0,0 +1,87 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::database::Database;
+use crate::{entities::local_profile::LocalProfile, TABLE_LOCAL_PROFILE};
+
+
+pub struct LocalProfileRepository {
+    pub db: Database,
+}
+
+impl LocalProfileRepository {
+    pub async fn get_by_slug(&self, slug: &str) -> Result<Option<LocalProfile>, tokio_rusqlite::Error> {
+        let slug_clone = slug.to_string();
+        self.db.read(move |conn| {
+            let query = format!("SELECT slug, name, pronouns, location, tagline, avatar, updated FROM {} WHERE slug = ?1", TABLE_LOCAL_PROFILE);
+            conn.query_row(query.as_str(), params![slug_clone], |row| {
+                Ok(LocalProfile {
+                    slug: row.get(0)?,
+                    name: row.get(1)?,
+                    pronouns: row.get(2)?,
+                    location: row.get(3)?,
+                    tagline: row.get(4)?,
+                    avatar: row.get(5)?,
+                    updated: row.get(6)?
+                })
+            }).optional()
+        }).await
+    }
+
+    // Get every profile without loading their avatars
+    pub async fn get_all(&self) -> Result<Vec<LocalProfile>, tokio_rusqlite::Error> {
+        self.db.read(move |conn| {
+            let query = format!("SELECT slug, name, pronouns, location, tagline, updated FROM {} ORDER BY slug", TABLE_LOCAL_PROFILE);
+            let mut stmt = conn.prepare(query.as_str())?;
+            let profiles = stmt.query_map([], |row| {
+                Ok(LocalProfile {
+                    slug: row.get(0)?,
+                    name: row.get(1)?,
+                    pronouns: row.get(2)?,
+                    location: row.get(3)?,
+                    tagline: row.get(4)?,
+                    avatar: None,
+                    updated: row.get(5)?
+                })
+            })?.collect::<Result<Vec<LocalProfile>, rusqlite::Error>>()?;
+
+            Ok(profiles)
+        }).await
+    }
+
+    // Insert or replace a profile, keeping the stored avatar when none is given
+    pub async fn upsert(&self, entity: LocalProfile) -> Result<(), tokio_rusqlite::Error> {
+        self.db.write(move |conn| {
+            let query = format!("INSERT INTO {} (slug, name, pronouns, location, tagline, avatar, updated)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ON CONFLICT(slug) DO UPDATE SET
+                    name = excluded.name,
+                    pronouns = excluded.pronouns,
+                    location = excluded.location,
+                    tagline = excluded.tagline,
+                    avatar = COALESCE(excluded.avatar, avatar),
+                    updated = excluded.updated", TABLE_LOCAL_PROFILE);
+            conn.execute(query.as_str(), params![
+                entity.slug,
+                entity.name,
+                entity.pronouns,
+                entity.location,
+                entity.tagline,
+                entity.avatar,
+                entity.updated
+                ]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    pub async fn delete(&self, slug: &str) -> Result<bool, tokio_rusqlite::Error> {
+        let slug_clone = slug.to_string();
+        self.db.write(move |conn| {
+            let query = format!("DELETE FROM {} WHERE slug = ?1", TABLE_LOCAL_PROFILE);
+            let deleted = conn.execute(query.as_str(), params![slug_clone])?;
+
+            Ok(deleted > 0)
+        }).await
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+
+use crate::entities::github_user::GithubUser;
+use crate::errors::AppError;
+use super::user_repository::UserRepository;
+
+
+// Users kept in memory only, for tests and deployments that don't need the cache to outlive the process.
+#[derive(Default)]
+pub struct MemoryUserRepository {
+    users: Mutex<HashMap<(String, i64), GithubUser>>,
+}
+
+#[async_trait]
+impl UserRepository for MemoryUserRepository {
+    async fn get_by_username(&self, provider: &str, username: &str) -> Result<Option<GithubUser>, AppError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.values()
+            .find(|user| user.provider == provider && user.username.eq_ignore_ascii_case(username))
+            .cloned())
+    }
+
+    async fn get_by_id(&self, provider: &str, id: i64) -> Result<Option<GithubUser>, AppError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.get(&(provider.to_string(), id)).cloned())
+    }
+
+    async fn upsert(&self, entity: GithubUser) -> Result<(), AppError> {
+        let mut users = self.users.lock().unwrap();
+        users.insert((entity.provider.clone(), entity.id), entity);
+        Ok(())
+    }
+
+    async fn update_expiration(&self, provider: &str, id: i64, expiration: i64) -> Result<(), AppError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(user) = users.get_mut(&(provider.to_string(), id)) {
+            user.expiration = expiration;
+        }
+        Ok(())
+    }
+
+    async fn get_all(&self, provider: Option<&str>) -> Result<Vec<GithubUser>, AppError> {
+        let users = self.users.lock().unwrap();
+        let mut users: Vec<GithubUser> = users.values()
+            .filter(|user| provider.is_none_or(|provider| user.provider == provider))
+            .cloned()
+            .collect();
+        users.sort_by_cached_key(|user| (user.provider.clone(), user.username.to_lowercase()));
+        Ok(users)
+    }
+
+    async fn delete_by_username(&self, provider: &str, username: &str) -> Result<bool, AppError> {
+        let mut users = self.users.lock().unwrap();
+        let count = users.len();
+        users.retain(|_, user| user.provider != provider || !user.username.eq_ignore_ascii_case(username));
+        Ok(users.len() < count)
+    }
+
+    async fn delete_all(&self) -> Result<usize, AppError> {
+        let mut users = self.users.lock().unwrap();
+        let count = users.len();
+        users.clear();
+        Ok(count)
+    }
+
+    async fn delete_expired(&self, now: i64) -> Result<usize, AppError> {
+        let mut users = self.users.lock().unwrap();
+        let count = users.len();
+        users.retain(|_, user| user.expiration > now);
+        Ok(count - users.len())
+    }
+
+    // Nothing to give back, removed users are freed right away
+    async fn vacuum(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::user_repository::tests::check_round_trip;
+
+    #[tokio::test]
+    async fn round_trip() {
+        check_round_trip(&MemoryUserRepository::default()).await;
+    }
+}
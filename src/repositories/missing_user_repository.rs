@@ -0,0 +1,61 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::database::Database;
+use crate::{entities::missing_user::MissingUser, TABLE_MISSING_USER};
+
+
+pub struct MissingUserRepository {
+    pub db: Database,
+}
+
+impl MissingUserRepository {
+    // Get a username recorded as missing that has not expired
+    pub async fn get_by_username(&self, provider: &str, username: &str, now: i64) -> Result<Option<MissingUser>, tokio_rusqlite::Error> {
+        let provider_clone = provider.to_string();
+        let username_clone = username.to_string();
+        self.db.read(move |conn| {
+            let query = format!("SELECT provider, username, expiration FROM {} WHERE provider = ?1 AND username = ?2 AND expiration > ?3", TABLE_MISSING_USER);
+            conn.query_row(query.as_str(), params![provider_clone, username_clone, now], |row| {
+                Ok(MissingUser {
+                    provider: row.get(0)?,
+                    username: row.get(1)?,
+                    expiration: row.get(2)?
+                })
+            }).optional()
+        }).await
+    }
+
+    pub async fn upsert(&self, entity: MissingUser) -> Result<(), tokio_rusqlite::Error> {
+        self.db.write(move |conn| {
+            let query = format!("INSERT INTO {} (provider, username, expiration)
+                VALUES (?1, ?2, ?3)
+                ON CONFLICT(provider, username) DO UPDATE SET
+                    expiration = excluded.expiration", TABLE_MISSING_USER);
+            conn.execute(query.as_str(), params![
+                entity.provider,
+                entity.username,
+                entity.expiration
+                ]
+            )?;
+
+            Ok(())
+        }).await
+    }
+
+    pub async fn delete(&self, provider: &str, username: &str) -> Result<usize, tokio_rusqlite::Error> {
+        let provider_clone = provider.to_string();
+        let username_clone = username.to_string();
+        self.db.write(move |conn| {
+            let query = format!("DELETE FROM {} WHERE provider = ?1 AND username = ?2", TABLE_MISSING_USER);
+            conn.execute(query.as_str(), params![provider_clone, username_clone])
+        }).await
+    }
+
+    // Delete the expired usernames
+    pub async fn evict(&self, now: i64) -> Result<usize, tokio_rusqlite::Error> {
+        self.db.write(move |conn| {
+            let query = format!("DELETE FROM {} WHERE expiration <= ?1", TABLE_MISSING_USER);
+            conn.execute(query.as_str(), params![now])
+        }).await
+    }
+}
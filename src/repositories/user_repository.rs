@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+
+use crate::entities::github_user::GithubUser;
+use crate::errors::AppError;
+
+
+// Storage of the cached profiles of every provider, keyed by provider and id.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    // Usernames are matched case insensitively
+    async fn get_by_username(&self, provider: &str, username: &str) -> Result<Option<GithubUser>, AppError>;
+
+    async fn get_by_id(&self, provider: &str, id: i64) -> Result<Option<GithubUser>, AppError>;
+
+    // Insert a user or replace the stored one with the same provider and id
+    async fn upsert(&self, entity: GithubUser) -> Result<(), AppError>;
+
+    async fn update_expiration(&self, provider: &str, id: i64, expiration: i64) -> Result<(), AppError>;
+
+    // Users of a provider, or of every provider without one, ordered by provider and username
+    async fn get_all(&self, provider: Option<&str>) -> Result<Vec<GithubUser>, AppError>;
+
+    async fn delete_by_username(&self, provider: &str, username: &str) -> Result<bool, AppError>;
+
+    async fn delete_all(&self) -> Result<usize, AppError>;
+
+    // Delete the users that expired at or before the given timestamp
+    async fn delete_expired(&self, now: i64) -> Result<usize, AppError>;
+
+    // Give the space of deleted users back to the file system
+    async fn vacuum(&self) -> Result<(), AppError>;
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn get_user(provider: &str, id: i64, username: &str) -> GithubUser {
+        GithubUser {
+            provider: provider.to_string(),
+            id,
+            username: username.to_string(),
+            name: Some(String::from("Mona")),
+            location: None,
+            avatar_url: format!("https://example.com/{}.png", id),
+            pronouns: Some(String::from("she/her")),
+            tagline: None,
+            pinned: Some(String::from("hello-world\nSpoon-Knife")),
+            sponsors: Some(3),
+            etag: Some(String::from("\"abc\"")),
+            last_modified: None,
+            expiration: 100,
+        }
+    }
+
+    // Checks every implementation is held to
+    pub async fn check_round_trip(repository: &dyn UserRepository) {
+        repository.upsert(get_user("github", 1, "Octocat")).await.unwrap();
+        repository.upsert(get_user("gitlab", 1, "octocat")).await.unwrap();
+        repository.upsert(get_user("github", 2, "Hubot")).await.unwrap();
+
+        let user = repository.get_by_username("github", "OCTOCAT").await.unwrap().unwrap();
+        assert_eq!((user.provider.as_str(), user.id, user.username.as_str()), ("github", 1, "Octocat"));
+        assert_eq!(user.pronouns.as_deref(), Some("she/her"));
+        assert_eq!(user.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(user.pinned.as_deref(), Some("hello-world\nSpoon-Knife"));
+        assert_eq!(user.sponsors, Some(3));
+        assert!(repository.get_by_username("gitea", "octocat").await.unwrap().is_none());
+
+        // Same provider and id replaces the user, such as after a rename
+        let mut renamed = get_user("github", 1, "mona");
+        renamed.expiration = 200;
+        repository.upsert(renamed).await.unwrap();
+        assert!(repository.get_by_username("github", "octocat").await.unwrap().is_none());
+        assert_eq!(repository.get_by_id("github", 1).await.unwrap().unwrap().username, "mona");
+
+        let usernames = |users: Vec<GithubUser>| users.into_iter().map(|user| format!("{}:{}", user.provider, user.username)).collect::<Vec<_>>();
+        assert_eq!(usernames(repository.get_all(None).await.unwrap()), ["github:Hubot", "github:mona", "gitlab:octocat"]);
+        assert_eq!(usernames(repository.get_all(Some("gitlab")).await.unwrap()), ["gitlab:octocat"]);
+
+        repository.update_expiration("github", 2, 300).await.unwrap();
+        assert_eq!(repository.get_by_id("github", 2).await.unwrap().unwrap().expiration, 300);
+        assert_eq!(repository.delete_expired(200).await.unwrap(), 2);
+        assert!(repository.delete_by_username("github", "HUBOT").await.unwrap());
+        assert!(!repository.delete_by_username("github", "hubot").await.unwrap());
+        assert_eq!(repository.delete_all().await.unwrap(), 0);
+    }
+}
@@ -1 +1,11 @@
-pub mod github_user_service;
\ No newline at end of file
+pub mod avatar_cache_service;
+pub mod avatar_service;
+pub mod github_api_service;
+pub mod github_auth_service;
+pub mod github_org_service;
+pub mod github_repo_service;
+pub mod github_user_service;
+pub mod local_profile_service;
+pub mod missing_user_service;
+pub mod refresh_service;
+pub mod render_service;
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::future::Future;
+
+use crate::entities::avatar_cache::AvatarCache;
+use crate::errors::AppError;
+use crate::mappers::github_user_mapper;
+use crate::models::avatar_cache_stats::AvatarCacheStats;
+use crate::renderers::card_renderer;
+use crate::repositories::avatar_cache_repository::AvatarCacheRepository;
+use crate::time;
+
+
+// Size avatars are cached at, twice the size they are drawn at on a card
+const AVATAR_SIZE: u32 = 200;
+
+// Milliseconds an avatar is used for before that is written down again, so hits are reads only.
+// Eviction only needs a rough order of use.
+const LAST_USED_INTERVAL: i64 = 60 * 1000;
+
+// Avatars resized ahead of time and kept for as long as the profile they belong to.
+pub struct AvatarCacheService {
+    pub repository: AvatarCacheRepository,
+    pub max_bytes: i64,
+}
+
+impl AvatarCacheService {
+    // Get a cached avatar, fetching and caching it on a miss
+    pub async fn get_or_fetch<F>(&self, key: &str, fetch: F) -> Result<Vec<u8>, AppError>
+    where
+        F: Future<Output = Result<Vec<u8>, AppError>>,
+    {
+        let now = time::get_timestamp_millis();
+        match self.repository.get_by_key(key, now).await {
+            Ok(Some(avatar)) => {
+                log::info!("Hit for avatar, key: {}!", key);
+                if now - avatar.last_used >= LAST_USED_INTERVAL {
+                    self.update_last_used_in_background(key, now);
+                }
+                return Ok(avatar.data);
+            },
+            Ok(None) => (),
+            // The cache is only an optimisation, fall back to fetching
+            Err(e) => log::error!("Failed to get avatar {}: {:?}", key, e)
+        }
+
+        self.fetch(key, fetch).await
+    }
+
+    fn update_last_used_in_background(&self, key: &str, now: i64) {
+        let repository = self.repository.clone();
+        let key = key.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = repository.update_last_used(&key, now).await {
+                log::error!("Failed to mark avatar {} as used: {:?}", key, e);
+            }
+        });
+    }
+
+    // Fetch an avatar and replace the cached one
+    pub async fn fetch<F>(&self, key: &str, fetch: F) -> Result<Vec<u8>, AppError>
+    where
+        F: Future<Output = Result<Vec<u8>, AppError>>,
+    {
+        log::info!("Miss for avatar, key: {}!", key);
+        let avatar = fetch.await?;
+        let avatar = tokio::task::spawn_blocking(move || card_renderer::normalize_avatar(&avatar, AVATAR_SIZE))
+            .await
+            .map_err(|e| AppError::Decode(e.to_string()))?
+            .map_err(|e| AppError::Decode(format!("Failed to decode avatar {}: {}", key, e)))?;
+
+        let now = time::get_timestamp_millis();
+        let entity = AvatarCache {
+            key: key.to_string(),
+            size: avatar.len() as i64,
+            data: avatar.clone(),
+            // Expire along with the profile row
+            expiration: github_user_mapper::next_expiration(),
+            last_used: now,
+        };
+        if let Err(e) = self.repository.upsert(entity).await {
+            log::error!("Failed to cache avatar {}: {:?}", key, e);
+            return Ok(avatar);
+        }
+        match self.repository.evict(self.max_bytes, now).await {
+            Ok(evicted) if evicted > 0 => log::info!("Evicted {} avatars from the cache", evicted),
+            Ok(_) => (),
+            Err(e) => log::error!("Failed to evict avatars: {:?}", e)
+        }
+
+        Ok(avatar)
+    }
+
+    pub async fn get_stats(&self) -> Result<AvatarCacheStats, AppError> {
+        let (entries, bytes) = self.repository.get_size().await?;
+        Ok(AvatarCacheStats {
+            entries,
+            bytes,
+            max_bytes: self.max_bytes,
+        })
+    }
+
+    pub async fn purge(&self) -> Result<usize, AppError> {
+        let purged = self.repository.delete_all().await?;
+        log::info!("Purged {} avatars from the cache", purged);
+        Ok(purged)
+    }
+}
@@ -0,0 +1,99 @@
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::errors::AppError;
+use crate::models::profile::Profile;
+use crate::providers::avatar_reader;
+use crate::renderers::{avatar_renderer, card_renderer};
+
+
+// Size avatars are requested and generated at
+const AVATAR_SIZE: u32 = 200;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarSource {
+    // The avatar of the forge or instance the profile comes from
+    #[default]
+    Provider,
+    Gravatar,
+    Libravatar,
+    Generated,
+}
+
+impl AvatarSource {
+    pub fn requires_email(&self) -> bool {
+        matches!(self, AvatarSource::Gravatar | AvatarSource::Libravatar)
+    }
+}
+
+// Avatars that do not come from the profile provider.
+pub struct AvatarService {
+    pub client: Arc<Client>,
+    // Base URLs of the Gravatar compatible APIs, which may point at a local mirror
+    pub gravatar_url: String,
+    pub libravatar_url: String,
+}
+
+impl AvatarService {
+    // Get the avatar of a profile from a source other than the provider.
+    // None means the provider avatar should be used instead.
+    pub async fn get_avatar(&self, source: AvatarSource, profile: &Profile, email: Option<&str>) -> Result<Option<Vec<u8>>, AppError> {
+        match source {
+            AvatarSource::Provider => Ok(None),
+            AvatarSource::Gravatar => self.get_by_email(&self.gravatar_url, email).await,
+            AvatarSource::Libravatar => self.get_by_email(&self.libravatar_url, email).await,
+            AvatarSource::Generated => AvatarService::get_generated(profile).map(Some)
+        }
+    }
+
+    pub fn get_generated(profile: &Profile) -> Result<Vec<u8>, AppError> {
+        // Seed with the provider so the same login on different forges looks different
+        AvatarService::generate(&format!("{}:{}", profile.provider, profile.login.to_lowercase()))
+    }
+
+    // Render an identicon from any seed, which is also the fallback for avatars that can't be decoded
+    pub fn generate(seed: &str) -> Result<Vec<u8>, AppError> {
+        avatar_renderer::render_identicon(&Sha256::digest(seed.as_bytes()), AVATAR_SIZE)
+            .map_err(|e| AppError::Decode(e.to_string()))
+    }
+
+    async fn get_by_email(&self, base_url: &str, email: Option<&str>) -> Result<Option<Vec<u8>>, AppError> {
+        let email = match email {
+            Some(email) => email,
+            None => return Err(AppError::Invalid(String::from("An email is required for this avatar source!")))
+        };
+        // Ask for a 404 rather than a placeholder so the provider avatar is used instead
+        let url = format!("{}/{}?s={}&d=404", base_url, AvatarService::hash_email(email), AVATAR_SIZE);
+        log::info!("Making request to {}...", url);
+        let response = self.client.get(url)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            log::error!("{:?}", response.status());
+            return Err(AppError::Upstream(format!("Failed to get avatar by email, status: {}", response.status())));
+        }
+
+        // Decoded and resized like provider avatars, so a broken image falls back to a generated one
+        let avatar = avatar_reader::read_avatar(response).await?;
+        let avatar = tokio::task::spawn_blocking(move || card_renderer::normalize_avatar(&avatar, AVATAR_SIZE))
+            .await
+            .map_err(|e| AppError::Decode(e.to_string()))?
+            .map_err(|e| AppError::Decode(format!("Failed to decode avatar by email: {}", e)))?;
+        Ok(Some(avatar))
+    }
+
+    // Gravatar and Libravatar both accept the SHA-256 hash of the normalised email
+    pub fn hash_email(email: &str) -> String {
+        Sha256::digest(email.trim().to_lowercase().as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
@@ -0,0 +1,195 @@
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::errors::AppError;
+use crate::models::cache_validators::CacheValidators;
+use crate::models::circuit_status::CircuitStatus;
+use crate::models::github_graphql::{GraphqlRateLimit, GraphqlResponse};
+use crate::providers::circuit_breaker::CircuitBreaker;
+use crate::providers::fixtures::FixtureStore;
+use crate::providers::rate_limit::RateLimitState;
+use crate::providers::retry::RetryPolicy;
+use crate::services::github_auth_service::GithubAuthService;
+use crate::time;
+
+
+// Shared access to the GitHub REST API, tracking the rate limit across every service using it.
+// Nothing is locked while a request is in flight, so requests for different resources run in parallel.
+pub struct GithubApiService {
+    pub client: Client,
+    pub rate_limit: RateLimitState,
+    // The GraphQL API has a point based limit of its own
+    pub graphql_rate_limit: RateLimitState,
+    // Base URL of the REST API, such as a GitHub Enterprise Server or a local mock
+    pub base_url: String,
+    pub graphql_url: String,
+    pub auth: GithubAuthService,
+    pub retry: RetryPolicy,
+    pub breaker: CircuitBreaker,
+    // Responses served from or recorded into a directory instead of only going to GitHub
+    pub fixtures: Option<FixtureStore>,
+}
+
+impl GithubApiService {
+    pub async fn get(&self, url: &str) -> Result<Response, AppError> {
+        match self.get_if_modified(url, &CacheValidators::default()).await? {
+            Some(response) => Ok(response),
+            None => Err(AppError::Upstream(String::from("Got a 304 response without validators!")))
+        }
+    }
+
+    // Send a conditional request, returning None if the resource has not been modified.
+    // GitHub does not count 304 responses against the primary rate limit.
+    pub async fn get_if_modified(&self, url: &str, validators: &CacheValidators) -> Result<Option<Response>, AppError> {
+        log::info!("Making request to {}...", url);
+
+        self.rate_limit.check("GitHub")?;
+        let authorization = if self.is_offline() { None } else { self.auth.get_authorization().await? };
+        let response = self.send(|| {
+            let mut request = self.client.get(url)
+                .header("Accept", "application/json");
+            if let Some(authorization) = &authorization {
+                request = request.header("Authorization", authorization);
+            }
+            if let Some(etag) = &validators.etag {
+                request = request.header("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header("If-Modified-Since", last_modified);
+            }
+            request
+        }).await?;
+        self.rate_limit.update(response.headers(), "x-ratelimit-remaining", "x-ratelimit-reset");
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            log::info!("Not modified: {}", url);
+            return Ok(None);
+        }
+        // Suspended and deleted accounts answer with a 410
+        if response.status() == StatusCode::NOT_FOUND || response.status() == StatusCode::GONE {
+            return Err(AppError::NotFound(url.to_string()));
+        }
+        if let Some(err) = self.rate_limit.get_limited_error(response.status()) {
+            log::warn!("Reached the rate limit for GitHub!");
+            return Err(err);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            log::error!("{:?}", status);
+            log::error!("{:?}", response.text().await.unwrap_or_default());
+            return Err(AppError::Upstream(format!("Failed to get response from GitHub, status: {}", status)));
+        }
+
+        Ok(Some(response))
+    }
+
+    // Run a GraphQL query, which needs authentication. Queries only read, so they are retried like GET requests.
+    pub async fn query<T: DeserializeOwned>(&self, query: &str, variables: serde_json::Value) -> Result<T, AppError> {
+        log::info!("Making GraphQL query to {}...", self.graphql_url);
+
+        self.graphql_rate_limit.check("GitHub GraphQL")?;
+        let authorization = match self.auth.get_authorization().await? {
+            Some(authorization) => authorization,
+            None => return Err(AppError::Invalid(String::from("The GitHub GraphQL API needs authentication!")))
+        };
+        let body = serde_json::json!({ "query": query, "variables": variables }).to_string();
+        let response = self.send(|| {
+            self.client.post(&self.graphql_url)
+                .header("Accept", "application/json")
+                .header("Content-Type", "application/json")
+                .header("Authorization", &authorization)
+                .body(body.clone())
+        }).await?;
+        self.graphql_rate_limit.update(response.headers(), "x-ratelimit-remaining", "x-ratelimit-reset");
+
+        if let Some(err) = self.graphql_rate_limit.get_limited_error(response.status()) {
+            log::warn!("Reached the GraphQL rate limit for GitHub!");
+            return Err(err);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            log::error!("{:?}", status);
+            log::error!("{:?}", response.text().await.unwrap_or_default());
+            return Err(AppError::Upstream(format!("Failed to get GraphQL response from GitHub, status: {}", status)));
+        }
+
+        let contents = response.text().await?;
+        let response: GraphqlResponse<T> = serde_json::from_str(&contents)?;
+        if let Some(error) = response.errors.first() {
+            return Err(match error.error_type.as_deref() {
+                Some("NOT_FOUND") => AppError::NotFound(error.message.clone()),
+                Some("RATE_LIMITED") => self.graphql_rate_limit
+                    .get_limited_error(StatusCode::TOO_MANY_REQUESTS)
+                    .unwrap_or(AppError::Upstream(error.message.clone())),
+                _ => AppError::Upstream(format!("GraphQL query failed: {}", error.message))
+            });
+        }
+        response.data.ok_or_else(|| AppError::Decode(String::from("GraphQL response has no data!")))
+    }
+
+    // Record the points left as reported in the body, which also tells the cost of the query
+    pub fn update_graphql_rate_limit(&self, rate_limit: &GraphqlRateLimit) {
+        let reset = chrono::DateTime::parse_from_rfc3339(&rate_limit.reset_at)
+            .map(|reset| reset.timestamp())
+            .unwrap_or_else(|_| time::get_timestamp());
+        log::debug!("GraphQL query cost {} points, {} left", rate_limit.cost, rate_limit.remaining);
+        self.graphql_rate_limit.set(rate_limit.remaining, reset);
+    }
+
+    // Send a read only request built by the closure, or serve it from the fixtures when there are any
+    pub async fn send<F>(&self, build: F) -> Result<Response, AppError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        match &self.fixtures {
+            Some(fixtures) if fixtures.record => {
+                let url = build().build()?.url().clone();
+                let response = self.send_with_retry(build).await?;
+                fixtures.record(&url, response).await
+            },
+            Some(fixtures) => fixtures.replay(build().build()?.url()).await,
+            None => self.send_with_retry(build).await
+        }
+    }
+
+    // Serving fixtures without recording them never reaches GitHub
+    pub fn is_offline(&self) -> bool {
+        self.fixtures.as_ref().is_some_and(|fixtures| !fixtures.record)
+    }
+
+    // Send a read only request, retrying connection failures and 5xx responses.
+    // Failures that outlast the retries count towards opening the circuit.
+    async fn send_with_retry<F>(&self, build: F) -> Result<Response, AppError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        self.breaker.check("GitHub")?;
+        let mut attempt = 1;
+        loop {
+            let result = build().send().await;
+            let is_retryable = match &result {
+                Ok(response) => RetryPolicy::is_retryable_status(response.status()),
+                Err(e) => RetryPolicy::is_retryable_error(e),
+            };
+            if !is_retryable || attempt >= self.retry.attempts {
+                match &result {
+                    Ok(response) if !RetryPolicy::is_retryable_status(response.status()) => self.breaker.record_success(),
+                    _ => self.breaker.record_failure("GitHub")
+                }
+                return Ok(result?);
+            }
+
+            let delay = self.retry.get_delay(attempt);
+            match &result {
+                Ok(response) => log::warn!("GitHub answered with {}, retrying in {}ms", response.status(), delay.as_millis()),
+                Err(e) => log::warn!("GitHub request failed, retrying in {}ms: {}", delay.as_millis(), e)
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    pub fn get_circuit_status(&self) -> CircuitStatus {
+        self.breaker.get_status()
+    }
+}
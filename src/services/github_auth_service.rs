@@ -0,0 +1,117 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use reqwest::Client;
+use tokio::sync::Mutex;
+
+use crate::errors::AppError;
+use crate::models::github_installation_token::GithubInstallationToken;
+use crate::time;
+
+
+// Seconds before expiry an installation token is replaced, so it never expires mid request
+const INSTALLATION_TOKEN_MARGIN: i64 = 5 * 60;
+
+pub enum GithubAuth {
+    Anonymous,
+    Token(String),
+    // Installation tokens are requested with a JWT signed by the private key of the app
+    App {
+        app_id: String,
+        installation_id: i64,
+        key: PKey<Private>,
+    },
+}
+
+pub struct GithubAuthService {
+    pub auth: GithubAuth,
+    pub base_url: String,
+    pub client: Client,
+    // Current installation token and the timestamp it expires at
+    installation_token: Mutex<Option<(String, i64)>>,
+}
+
+impl GithubAuthService {
+    pub fn new(auth: GithubAuth, base_url: String, client: Client) -> GithubAuthService {
+        GithubAuthService {
+            auth,
+            base_url,
+            client,
+            installation_token: Mutex::new(None),
+        }
+    }
+
+    // Describe the auth mode for logs, without any secrets
+    pub fn mode(&self) -> String {
+        match &self.auth {
+            GithubAuth::Anonymous => String::from("anonymous"),
+            GithubAuth::Token(_) => String::from("token"),
+            GithubAuth::App { app_id, installation_id, .. } => format!("GitHub App {}, installation {}", app_id, installation_id),
+        }
+    }
+
+    // Get the value of the Authorization header, if any
+    pub async fn get_authorization(&self) -> Result<Option<String>, AppError> {
+        match &self.auth {
+            GithubAuth::Anonymous => Ok(None),
+            GithubAuth::Token(token) => Ok(Some(format!("Bearer {}", token))),
+            GithubAuth::App { app_id, installation_id, key } => {
+                let mut installation_token = self.installation_token.lock().await;
+                if let Some((token, expiration)) = installation_token.as_ref() {
+                    if *expiration - INSTALLATION_TOKEN_MARGIN > time::get_timestamp() {
+                        return Ok(Some(format!("Bearer {}", token)));
+                    }
+                }
+
+                let (token, expiration) = self.create_installation_token(app_id, *installation_id, key).await?;
+                *installation_token = Some((token.clone(), expiration));
+                Ok(Some(format!("Bearer {}", token)))
+            }
+        }
+    }
+
+    async fn create_installation_token(&self, app_id: &str, installation_id: i64, key: &PKey<Private>) -> Result<(String, i64), AppError> {
+        let url = format!("{}/app/installations/{}/access_tokens", self.base_url, installation_id);
+        log::info!("Making request to {}...", url);
+        let response = self.client.post(url)
+            .header("Accept", "application/vnd.github+json")
+            .header("Authorization", format!("Bearer {}", GithubAuthService::create_jwt(app_id, key)?))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            log::error!("{:?}", response.status());
+            return Err(AppError::Upstream(format!("Failed to create an installation token, status: {}", response.status())));
+        }
+
+        let installation_token: GithubInstallationToken = serde_json::from_str(&response.text().await?)?;
+        let expiration = chrono::DateTime::parse_from_rfc3339(&installation_token.expires_at)
+            .map_err(|e| AppError::Decode(e.to_string()))?
+            .timestamp();
+        log::info!("Created an installation token for GitHub App {}, expires at {}", app_id, installation_token.expires_at);
+        Ok((installation_token.token, expiration))
+    }
+
+    // Create a JWT identifying the app, valid for the few minutes it takes to get an installation token
+    fn create_jwt(app_id: &str, key: &PKey<Private>) -> Result<String, AppError> {
+        let now = time::get_timestamp();
+        let header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+        // Issued in the past to allow for clock drift
+        let claims = serde_json::json!({ "iat": now - 60, "exp": now + 9 * 60, "iss": app_id });
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+
+        let sign = || -> Result<Vec<u8>, openssl::error::ErrorStack> {
+            let mut signer = Signer::new(MessageDigest::sha256(), key)?;
+            signer.update(message.as_bytes())?;
+            signer.sign_to_vec()
+        };
+        let signature = sign().map_err(|e| AppError::Internal(format!("Failed to sign the GitHub App JWT: {}", e)))?;
+        Ok(format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature)))
+    }
+}
@@ -0,0 +1,76 @@
+use axum::http::HeaderMap;
+use std::sync::Arc;
+use urlencoding::encode;
+
+use crate::errors::AppError;
+use crate::mappers::github_org_mapper;
+use crate::{models::github_org::GithubOrg, repositories::github_org_repository::GithubOrgRepository};
+use crate::services::github_api_service::GithubApiService;
+
+
+pub struct GithubOrgService {
+    pub repository: GithubOrgRepository,
+    pub api: Arc<GithubApiService>,
+}
+
+impl GithubOrgService {
+    pub async fn get_by_login(&self, login: &str) -> Result<Option<GithubOrg>, AppError> {
+        match self.repository.get_by_login(login).await? {
+            Some(org) => {
+                // Check if organisation in database cache is expired
+                let current_timestamp = chrono::prelude::Utc::now().timestamp_millis();
+                if current_timestamp >= org.expiration {
+                    // Miss
+                    log::info!("Expired timestamp, org: {}!", login);
+                    return self.update_org(login).await;
+                }
+                // Hit
+                log::info!("Hit for GitHub org, org: {}!", login);
+                Ok(Some(github_org_mapper::to_model(&org)))
+            },
+            None => self.update_org(login).await
+        }
+    }
+
+    async fn update_org(&self, login: &str) -> Result<Option<GithubOrg>, AppError> {
+        log::info!("Miss for GitHub org, org: {}!", login);
+        let url = format!("{}/orgs/{}", self.api.base_url, encode(login));
+        let response = match self.api.get(&url).await {
+            Ok(response) => response,
+            Err(AppError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e)
+        };
+        let contents = response.text().await?;
+        let mut org: GithubOrg = serde_json::from_str(&contents)?;
+
+        // Ask for a single member per page so the last page number is the member count
+        let url = format!("{}/orgs/{}/public_members?per_page=1", self.api.base_url, encode(login));
+        let response = self.api.get(&url).await?;
+        org.public_members = match GithubOrgService::get_last_page(response.headers()) {
+            Some(last_page) => last_page,
+            None => serde_json::from_str::<Vec<serde_json::Value>>(&response.text().await?)?.len() as i64
+        };
+
+        log::trace!("Upserting by org login: {}", org.login);
+        if let Err(e) = self.repository.upsert(github_org_mapper::to_entity(&org)).await {
+            log::error!("Failed to upsert org: {}", org.login);
+            log::error!("{:?}", e);
+            return Err(AppError::Storage(String::from("Failed to upsert org!")));
+        }
+
+        Ok(Some(org))
+    }
+
+    // Get the page number of the rel="last" link in a paginated response
+    fn get_last_page(header_map: &HeaderMap) -> Option<i64> {
+        let link = header_map.get("link")?.to_str().ok()?;
+        link.split(',')
+            .find(|part| part.contains("rel=\"last\""))
+            .and_then(|part| {
+                let url = part.split(';').next()?.trim().trim_start_matches('<').trim_end_matches('>');
+                url.split(['?', '&'])
+                    .find_map(|pair| pair.strip_prefix("page="))
+                    .and_then(|page| page.parse().ok())
+            })
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use urlencoding::encode;
+
+use crate::errors::AppError;
+use crate::mappers::github_repo_mapper;
+use crate::{models::github_repo::GithubRepo, repositories::github_repo_repository::GithubRepoRepository};
+use crate::services::github_api_service::GithubApiService;
+
+
+pub struct GithubRepoService {
+    pub repository: GithubRepoRepository,
+    pub api: Arc<GithubApiService>,
+}
+
+impl GithubRepoService {
+    pub async fn get_by_name(&self, owner: &str, repo: &str) -> Result<Option<GithubRepo>, AppError> {
+        let full_name = format!("{}/{}", owner, repo);
+        match self.repository.get_by_full_name(&full_name).await? {
+            Some(stored_repo) => {
+                // Check if repository in database cache is expired
+                let current_timestamp = chrono::prelude::Utc::now().timestamp_millis();
+                if current_timestamp >= stored_repo.expiration {
+                    // Miss
+                    log::info!("Expired timestamp, repo: {}!", full_name);
+                    return self.update_repo(owner, repo).await;
+                }
+                // Hit
+                log::info!("Hit for GitHub repo, repo: {}!", full_name);
+                Ok(Some(github_repo_mapper::to_model(&stored_repo)))
+            },
+            None => self.update_repo(owner, repo).await
+        }
+    }
+
+    async fn update_repo(&self, owner: &str, repo: &str) -> Result<Option<GithubRepo>, AppError> {
+        log::info!("Miss for GitHub repo, repo: {}/{}!", owner, repo);
+        let url = format!("{}/repos/{}/{}", self.api.base_url, encode(owner), encode(repo));
+        let response = match self.api.get(&url).await {
+            Ok(response) => response,
+            Err(AppError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e)
+        };
+        let contents = response.text().await?;
+        let github_repo: GithubRepo = serde_json::from_str(&contents)?;
+
+        log::trace!("Upserting by repo full name: {}", github_repo.full_name);
+        if let Err(e) = self.repository.upsert(github_repo_mapper::to_entity(&github_repo)).await {
+            log::error!("Failed to upsert repo: {}", github_repo.full_name);
+            log::error!("{:?}", e);
+            return Err(AppError::Storage(String::from("Failed to upsert repo!")));
+        }
+
+        Ok(Some(github_repo))
+    }
+}
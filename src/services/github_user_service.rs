@@ -1,130 +1,322 @@
-use std::cmp::Ordering;
-use std::error::Error;
-use axum::http::{HeaderMap};
-use tokio::sync::Mutex;
-use reqwest::Client;
-use std::sync::Arc;
-use urlencoding::encode;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
 
+use crate::entities::github_user::GithubUser;
+use crate::errors::AppError;
 use crate::mappers::github_user_mapper;
-use crate::{models::github_user::GithubUser, repositories::github_user_repository::GithubUserRepository};
+use crate::models::cached::Cached;
+use crate::models::cached_user::CachedUser;
+use crate::models::profile::Profile;
+use crate::repositories::user_repository::UserRepository;
+use crate::providers::github_provider::GithubProvider;
+use crate::providers::profile_provider::{ProfileFetch, ProfileProvider};
+use crate::services::avatar_cache_service::AvatarCacheService;
+use crate::services::avatar_service::{AvatarService, AvatarSource};
+use crate::services::missing_user_service::MissingUserService;
 use crate::time;
 
 
+pub static DEFAULT_PROVIDER: &str = "github";
+
+// Result of a fetch shared by every request waiting on it
+type SharedFetch = Arc<OnceCell<Result<Option<Profile>, AppError>>>;
+
 pub struct GithubUserService {
-    pub repository: GithubUserRepository,
-    pub client: Arc<Mutex<Client>>,
-    pub image_client: Arc<Client>,
-    pub remaining: Arc<Mutex<i64>>,
-    pub reset: Arc<Mutex<i64>>,
-    pub retry_after: Arc<Mutex<i64>>,
+    pub repository: Arc<dyn UserRepository>,
+    pub github: Arc<GithubProvider>,
+    pub providers: HashMap<String, Arc<dyn ProfileProvider>>,
+    pub avatars: AvatarService,
+    pub avatar_cache: AvatarCacheService,
+    pub missing: MissingUserService,
+    // Fetches in flight keyed by provider and lowercased username, so concurrent misses share one fetch
+    in_flight: Mutex<HashMap<String, SharedFetch>>,
+    // Users refreshed in the background, so a burst of stale hits spawns a single refresh
+    refreshing: Mutex<HashSet<String>>,
+    // Milliseconds past expiration a user is still served while it is refreshed in the background
+    max_stale: i64,
 }
 
 impl GithubUserService {
-    pub async fn get_by_username(&self, username: &str) -> Result<Option<GithubUser>, Box<dyn Error + Send + Sync>> {
-        let username_clone = username.clone();
-        let stored_user_option = self.repository.get_by_username(username).await;
-        let result_option: Result<Option<GithubUser>, Box<dyn Error + Send + Sync>> = match stored_user_option {
+    pub fn new(repository: Arc<dyn UserRepository>, github: Arc<GithubProvider>, providers: HashMap<String, Arc<dyn ProfileProvider>>, avatars: AvatarService, avatar_cache: AvatarCacheService, missing: MissingUserService, max_stale: i64) -> GithubUserService {
+        GithubUserService {
+            repository,
+            github,
+            providers,
+            avatars,
+            avatar_cache,
+            missing,
+            in_flight: Mutex::new(HashMap::new()),
+            refreshing: Mutex::new(HashSet::new()),
+            max_stale,
+        }
+    }
+
+    pub fn has_provider(&self, provider: &str) -> bool {
+        self.providers.contains_key(provider)
+    }
+
+    pub fn get_remaining(&self, provider: &str) -> Option<i64> {
+        self.providers.get(provider)?.remaining()
+    }
+
+    // Refresh a user ahead of its expiration, revalidating the stored user if there is one
+    pub async fn refresh(&self, provider: &str, username: &str) -> Result<Option<Profile>, AppError> {
+        let stored_user = self.repository.get_by_username(provider, username).await?;
+        self.update_user_once(provider, username, stored_user).await
+    }
+
+    pub async fn get_cached_users(&self, provider: Option<&str>) -> Result<Vec<CachedUser>, AppError> {
+        let now = time::get_timestamp_millis();
+        Ok(self.repository.get_all(provider).await?
+            .iter()
+            .map(|user| github_user_mapper::to_cached_user(user, now))
+            .collect())
+    }
+
+    pub async fn get_cached_user(&self, provider: &str, username: &str) -> Result<Option<CachedUser>, AppError> {
+        let now = time::get_timestamp_millis();
+        Ok(self.repository.get_by_username(provider, username).await?
+            .map(|user| github_user_mapper::to_cached_user(&user, now)))
+    }
+
+    // Fetch a user again without its validators, even if it is remembered as missing
+    pub async fn force_refresh(&self, provider: &str, username: &str) -> Result<Option<CachedUser>, AppError> {
+        self.missing.forget(provider, username).await?;
+        match self.update_user_once(provider, username, None).await? {
+            Some(user) => self.get_cached_user(provider, &user.login).await,
+            None => Ok(None)
+        }
+    }
+
+    pub async fn purge_user(&self, provider: &str, username: &str) -> Result<bool, AppError> {
+        log::info!("Purging {} user, username: {}!", provider, username);
+        self.repository.delete_by_username(provider, username).await
+    }
+
+    // Purge every cached user, or only the expired ones
+    pub async fn purge_users(&self, expired_only: bool) -> Result<usize, AppError> {
+        let purged = if expired_only {
+            self.repository.delete_expired(time::get_timestamp_millis()).await?
+        } else {
+            self.repository.delete_all().await?
+        };
+        log::info!("Purged {} cached users!", purged);
+        Ok(purged)
+    }
+
+    pub async fn vacuum(&self) -> Result<(), AppError> {
+        self.repository.vacuum().await
+    }
+
+    pub async fn get_by_username(self: &Arc<Self>, provider: &str, username: &str) -> Result<Option<Profile>, AppError> {
+        Ok(self.get_cached_by_username(provider, username).await?.map(|user| user.value))
+    }
+
+    // Get a user, serving an expired user while it is refreshed in the background
+    pub async fn get_cached_by_username(self: &Arc<Self>, provider: &str, username: &str) -> Result<Option<Cached<Profile>>, AppError> {
+        let username_clone = username;
+        let stored_user_option = self.repository.get_by_username(provider, username).await?;
+        let result_option: Result<Option<Cached<Profile>>, AppError> = match stored_user_option {
             Some(user) => {
                 // Check if user in database cache is expired
                 let current_timestamp = chrono::prelude::Utc::now().timestamp_millis();
-                if current_timestamp >= user.expiration {
-                    // Miss
-                    log::info!("Expired timestamp, username: {}!", username_clone);
-                    return self.update_user(username).await;
+                let stale_for = current_timestamp - user.expiration;
+                if stale_for >= self.max_stale {
+                    // Miss, too stale to serve
+                    log::info!("Expired timestamp, provider: {}, username: {}!", provider, username_clone);
+                    return match self.update_user_once(provider, username, Some(user.clone())).await {
+                        Ok(fresh_user) => Ok(fresh_user.map(GithubUserService::to_fresh)),
+                        // While the provider is known to be down, any stored user beats an error
+                        Err(AppError::Unavailable { .. }) => Ok(Some(Cached {
+                            value: github_user_mapper::to_model(&user),
+                            stale_for: Some(stale_for / 1000),
+                        })),
+                        Err(e) => Err(e)
+                    };
+                }
+                if stale_for >= 0 {
+                    // Stale, refresh in the background and serve what we have
+                    log::info!("Stale for {} user, username: {}!", provider, username_clone);
+                    self.refresh_in_background(provider, username, user.clone());
+                    return Ok(Some(Cached {
+                        value: github_user_mapper::to_model(&user),
+                        stale_for: Some(stale_for / 1000),
+                    }));
                 }
                 // Hit
-                log::info!("Hit for GitHub user, username: {}!", username_clone);
-                Ok(Some(github_user_mapper::to_model(&user)))
+                log::info!("Hit for {} user, username: {}!", provider, username_clone);
+                Ok(Some(GithubUserService::to_fresh(github_user_mapper::to_model(&user))))
             },
-            None => self.update_user(username).await
+            None => {
+                // Users the provider doesn't know are not looked up again until the negative cache expires
+                if self.missing.is_missing(provider, username).await? {
+                    log::info!("Negative hit for {} user, username: {}!", provider, username_clone);
+                    return Ok(None);
+                }
+                Ok(self.update_user_once(provider, username, None).await?.map(GithubUserService::to_fresh))
+            }
         };
         result_option
     }
 
-    pub async fn get_avatar_by_id(&self, id: i32) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
-        let url = format!("https://avatars.githubusercontent.com/u/{}?v=4", id);
-        let response = self.image_client.get(url)
-            .header("User-Agent", "BlossomiShymae/smol-profile-card")
-            .send()
-            .await
-            .expect("Failed to get avatar!");
+    fn to_fresh(user: Profile) -> Cached<Profile> {
+        Cached { value: user, stale_for: None }
+    }
 
-        if !response.status().is_success() {
-            log::error!("{:?}", response.status());
-            log::error!("{:?}", response.text().await.unwrap());
-            return Err(String::from("Failed to get response for avatar!"))?;
+    fn refresh_in_background(self: &Arc<Self>, provider: &str, username: &str, stored_user: GithubUser) {
+        let key = format!("{}:{}", provider, username.to_lowercase());
+        if !self.refreshing.lock().unwrap().insert(key.clone()) {
+            return;
         }
+        let service = self.clone();
+        let provider = provider.to_string();
+        let username = username.to_string();
+        tokio::spawn(async move {
+            // Failures keep the stale user, which is served until it reaches the max stale age
+            if let Err(e) = service.update_user_once(&provider, &username, Some(stored_user)).await {
+                log::warn!("Failed to refresh {} user {}, serving stale data: {}", provider, username, e);
+            }
+            service.refreshing.lock().unwrap().remove(&key);
+        });
+    }
 
-        let data = response.bytes().await.expect("Failed to get bytes!");
-        Ok(data.to_vec())
+    pub async fn get_avatar(&self, profile: &Profile) -> Result<Vec<u8>, AppError> {
+        // Profiles without an avatar, as GitLab and fediverse users may be, get a generated one
+        if profile.avatar_url.is_empty() {
+            return AvatarService::get_generated(profile);
+        }
+        let provider = self.get_provider(&profile.provider)?;
+        let result = self.avatar_cache
+            .get_or_fetch(&GithubUserService::get_avatar_key(profile), provider.fetch_avatar(profile))
+            .await;
+        GithubUserService::or_generated(result, || AvatarService::get_generated(profile))
     }
 
-    async fn update_user(&self, username: &str) -> Result<Option<GithubUser>, Box<dyn Error + Send + Sync>> {
-        log::info!("Miss for GitHub user, username: {}!", username);
-        let url = format!("https://api.github.com/users/{}", encode(username));
-        log::info!("Making request to {}...", url);
+    // Fetch the avatar of a profile again, replacing the cached one
+    pub async fn refresh_avatar(&self, profile: &Profile) -> Result<Vec<u8>, AppError> {
+        if profile.avatar_url.is_empty() {
+            return AvatarService::get_generated(profile);
+        }
+        let provider = self.get_provider(&profile.provider)?;
+        self.avatar_cache
+            .fetch(&GithubUserService::get_avatar_key(profile), provider.fetch_avatar(profile))
+            .await
+    }
 
-        let client = self.client.lock().await;
-        let mut remaining = self.remaining.lock().await;
-        let mut reset = self.reset.lock().await;
-        let mut retry_after = self.retry_after.lock().await;
-        // If retry after timestamp has not expired
-        if retry_after.cmp(&time::get_timestamp()) == Ordering::Greater {
-            log::warn!("Reached the secondary rate limit for GitHub!");
-            return Err(String::from("Reached the secondary rate limit!"))?;
+    pub async fn get_avatar_by_id(&self, id: i64) -> Result<Vec<u8>, AppError> {
+        let key = format!("{}:{}", DEFAULT_PROVIDER, id);
+        let result = self.avatar_cache
+            .get_or_fetch(&key, self.github.get_avatar_by_id(id))
+            .await;
+        GithubUserService::or_generated(result, || AvatarService::generate(&key))
+    }
+
+    // Avatars that are too large, of the wrong type or corrupt are replaced by a generated one
+    fn or_generated<F>(result: Result<Vec<u8>, AppError>, generate: F) -> Result<Vec<u8>, AppError>
+    where
+        F: FnOnce() -> Result<Vec<u8>, AppError>,
+    {
+        match result {
+            Err(AppError::Decode(e)) => {
+                log::warn!("Falling back to a generated avatar: {}", e);
+                generate()
+            },
+            result => result
         }
-        // If remaining requests is zero and the reset timestamp has not expired
-        if remaining.cmp(&0) == Ordering::Equal && reset.cmp(&time::get_timestamp()) == Ordering::Greater {
-            log::warn!("Reached the request limit for GitHub!");
-            return Err(String::from("Reached the request limit!"))?;
+    }
+
+    // Avatars are keyed by the user and a version taken from the avatar URL, which changes along with the avatar
+    fn get_avatar_key(profile: &Profile) -> String {
+        let version: String = Sha256::digest(profile.avatar_url.as_bytes())
+            .iter()
+            .take(8)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("{}:{}:{}", profile.provider, profile.id, version)
+    }
+
+    // Get the avatar of a profile from the given source, falling back to the provider avatar
+    pub async fn get_avatar_from(&self, profile: &Profile, source: AvatarSource, email: Option<&str>) -> Result<Vec<u8>, AppError> {
+        match self.avatars.get_avatar(source, profile, email).await {
+            Ok(Some(avatar)) => Ok(avatar),
+            Ok(None) => self.get_avatar(profile).await,
+            Err(e) => GithubUserService::or_generated(Err(e), || AvatarService::get_generated(profile))
         }
-        let response = client.get(url)
-            .header("User-Agent", "BlossomiShymae/smol-profile-card")
-            .header("Accept", "application/json")
-            .send()
+    }
+
+    fn get_provider(&self, provider: &str) -> Result<&Arc<dyn ProfileProvider>, AppError> {
+        self.providers
+            .get(provider)
+            .ok_or_else(|| AppError::Invalid(format!("Unknown provider: {}", provider)))
+    }
+
+    // Fetch a user, joining the fetch already in flight for the same user if there is one
+    async fn update_user_once(&self, provider: &str, username: &str, stored_user: Option<GithubUser>) -> Result<Option<Profile>, AppError> {
+        let key = format!("{}:{}", provider, username.to_lowercase());
+        let cell = self.in_flight.lock().unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let result = cell
+            .get_or_init(|| async {
+                self.update_user(provider, username, stored_user.as_ref()).await
+            })
             .await
-            .expect("Failed to get response for user!");
-
-        let header_map = response.headers();
-        let new_remaining: i64 = GithubUserService::get_int(header_map, "x-ratelimit-remaining");
-        let new_reset: i64 = GithubUserService::get_int(header_map, "x-ratelimit-reset");
-        let new_retry_after: i64 = GithubUserService::get_int(header_map, "retry-after");
-        *remaining = new_remaining;
-        *reset = new_reset;
-        *retry_after = time::get_timestamp() + new_retry_after;
-
-        if !response.status().is_success() {
-            log::error!("{:?}", response.status());
-            log::error!("{:?}", response.text().await.unwrap());
-            return Err(String::from("Failed to get response!"))?;
+            .clone();
+
+        // The first request to finish clears the entry, later misses fetch again
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.get(&key).is_some_and(|current| Arc::ptr_eq(current, &cell)) {
+            in_flight.remove(&key);
         }
+        result
+    }
+
+    // Fetch a user, revalidating the stored user with its validators if there is one
+    async fn update_user(&self, provider: &str, username: &str, stored_user: Option<&GithubUser>) -> Result<Option<Profile>, AppError> {
+        log::info!("Miss for {} user, username: {}!", provider, username);
+        let validators = stored_user
+            .map(github_user_mapper::to_validators)
+            .unwrap_or_default();
+        let (user, validators) = match self.get_provider(provider)?.fetch_profile_if_modified(username, &validators).await? {
+            ProfileFetch::Modified(user, validators) => (*user, validators),
+            ProfileFetch::NotModified => match stored_user {
+                Some(stored_user) => return self.extend_user(stored_user).await,
+                // Validators are only sent for stored users
+                None => return Err(AppError::Upstream(format!("Got a 304 for {} user without validators: {}", provider, username)))
+            },
+            ProfileFetch::NotFound => {
+                if let Err(e) = self.missing.record(provider, username).await {
+                    log::error!("Failed to record missing user: {}", username);
+                    log::error!("{:?}", e);
+                }
+                return Ok(None);
+            }
+        };
 
-        let contents = response.text().await.expect("Failed to get response!");
-        let user: GithubUser = serde_json::from_str(&contents).expect("Failed to deserialize GithubUser");
         log::trace!("Upserting by login name: {}", user.login);
-        let upsert_result = self.repository.upsert(github_user_mapper::to_entity(&user)).await;
+        let upsert_result = self.repository.upsert(github_user_mapper::to_entity(&user, &validators)).await;
 
         match upsert_result {
             Ok(()) => Ok(Some(user)),
             Err(e) => {
                 log::error!("Failed to upsert user: {}", user.login);
                 log::error!("{:?}", e);
-                return Err(String::from("Failed to upsert user!"))?;
+                Err(AppError::Storage(String::from("Failed to upsert user!")))
             }
         }
     }
 
-    fn get_int(header_map: &HeaderMap, key: &str) -> i64 {
-        match header_map.get(key) {
-            Some(value_option) => match value_option.to_str() {
-                Ok(str) => match str.to_string().parse() {
-                    Ok(value) => value,
-                    Err(_) => 0
-                },
-                Err(_) => 0
-            },
-            None => 0
+    // Keep a user that has not been modified for another day
+    async fn extend_user(&self, stored_user: &GithubUser) -> Result<Option<Profile>, AppError> {
+        log::info!("Not modified {} user, username: {}!", stored_user.provider, stored_user.username);
+        if let Err(e) = self.repository.update_expiration(&stored_user.provider, stored_user.id, github_user_mapper::next_expiration()).await {
+            log::error!("Failed to extend user: {}", stored_user.username);
+            log::error!("{:?}", e);
         }
+        Ok(Some(github_user_mapper::to_model(stored_user)))
     }
-}
\ No newline at end of file
+}
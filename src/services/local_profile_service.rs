@@ -0,0 +1,36 @@
+use crate::errors::AppError;
+use crate::mappers::local_profile_mapper;
+use crate::{models::local_profile::LocalProfile, repositories::local_profile_repository::LocalProfileRepository};
+
+
+pub struct LocalProfileService {
+    pub repository: LocalProfileRepository,
+}
+
+impl LocalProfileService {
+    pub async fn get_by_slug(&self, slug: &str) -> Result<Option<LocalProfile>, AppError> {
+        let profile = self.repository.get_by_slug(slug).await?;
+        Ok(profile.map(|profile| local_profile_mapper::to_model(&profile)))
+    }
+
+    pub async fn get_all(&self) -> Result<Vec<LocalProfile>, AppError> {
+        let profiles = self.repository.get_all().await?;
+        Ok(profiles.iter().map(local_profile_mapper::to_model).collect())
+    }
+
+    // Create or update a profile. A new profile must come with an avatar.
+    pub async fn save(&self, profile: LocalProfile) -> Result<LocalProfile, AppError> {
+        if profile.avatar.is_none() && self.repository.get_by_slug(&profile.slug).await?.is_none() {
+            return Err(AppError::Invalid(String::from("A new local profile needs an avatar!")));
+        }
+        log::info!("Saving local profile, slug: {}!", profile.slug);
+        self.repository.upsert(local_profile_mapper::to_entity(&profile)).await?;
+
+        Ok(LocalProfile { avatar: None, ..profile })
+    }
+
+    pub async fn delete(&self, slug: &str) -> Result<bool, AppError> {
+        log::info!("Deleting local profile, slug: {}!", slug);
+        Ok(self.repository.delete(slug).await?)
+    }
+}
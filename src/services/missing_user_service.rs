@@ -0,0 +1,39 @@
+use crate::entities::missing_user::MissingUser;
+use crate::errors::AppError;
+use crate::repositories::missing_user_repository::MissingUserRepository;
+use crate::time;
+
+
+// Remembers usernames the providers don't know, so typos and probes don't spend the rate limit on every request.
+pub struct MissingUserService {
+    pub repository: MissingUserRepository,
+    // Milliseconds a missing username is remembered for
+    pub ttl: i64,
+}
+
+impl MissingUserService {
+    pub async fn is_missing(&self, provider: &str, username: &str) -> Result<bool, AppError> {
+        let missing_user = self.repository.get_by_username(provider, username, time::get_timestamp_millis()).await?;
+        Ok(missing_user.is_some())
+    }
+
+    pub async fn record(&self, provider: &str, username: &str) -> Result<(), AppError> {
+        log::info!("Missing {} user, username: {}!", provider, username);
+        let now = time::get_timestamp_millis();
+        self.repository.upsert(MissingUser {
+            provider: provider.to_string(),
+            username: username.to_string(),
+            expiration: now + self.ttl,
+        }).await?;
+        // Expired usernames are cleared whenever a new one is recorded
+        self.repository.evict(now).await?;
+
+        Ok(())
+    }
+
+    // Look a username up again on the next request
+    pub async fn forget(&self, provider: &str, username: &str) -> Result<(), AppError> {
+        self.repository.delete(provider, username).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::models::refresh_status::{RefreshDecision, RefreshStatus, TrackedUser};
+use crate::services::github_user_service::GithubUserService;
+use crate::time;
+
+
+// Most users tracked at once, requests for other users are not counted until counts decay
+const MAX_TRACKED_USERS: usize = 10_000;
+// Tracked users shown on the admin endpoint
+const MAX_SHOWN_USERS: usize = 50;
+
+// Refreshes the most requested profiles shortly before they expire, so requests for them never miss.
+pub struct RefreshService {
+    pub users: Arc<GithubUserService>,
+    // Seconds between runs, 0 disables the scheduler
+    pub interval: u64,
+    pub top: usize,
+    // Milliseconds before expiration a profile is due for a refresh
+    pub ahead: i64,
+    pub reserve: i64,
+    // Requests per provider and lowercased username, halved every run so old popularity fades
+    counts: Mutex<HashMap<(String, String), u64>>,
+    status: Mutex<RefreshStatus>,
+}
+
+impl RefreshService {
+    pub fn new(users: Arc<GithubUserService>, interval: u64, top: usize, ahead: i64, reserve: i64) -> RefreshService {
+        let status = RefreshStatus {
+            enabled: interval > 0,
+            interval,
+            top,
+            ahead: ahead / 1000,
+            reserve,
+            ..RefreshStatus::default()
+        };
+        RefreshService {
+            users,
+            interval,
+            top,
+            ahead,
+            reserve,
+            counts: Mutex::new(HashMap::new()),
+            status: Mutex::new(status),
+        }
+    }
+
+    pub fn record(&self, provider: &str, username: &str) {
+        let mut counts = self.counts.lock().unwrap();
+        let key = (provider.to_string(), username.to_lowercase());
+        if counts.len() >= MAX_TRACKED_USERS && !counts.contains_key(&key) {
+            return;
+        }
+        *counts.entry(key).or_default() += 1;
+    }
+
+    pub fn get_status(&self) -> RefreshStatus {
+        let mut status = self.status.lock().unwrap().clone();
+        status.tracked = self.get_popular(MAX_SHOWN_USERS)
+            .into_iter()
+            .map(|(provider, username, requests)| TrackedUser { provider, username, requests })
+            .collect();
+        status
+    }
+
+    pub async fn run(self: Arc<Self>) {
+        if self.interval == 0 {
+            log::info!("Refresh scheduler is disabled");
+            return;
+        }
+        log::info!("Refresh scheduler: every {}s, top {} users, {}s ahead of expiration, reserving {} requests",
+            self.interval, self.top, self.ahead / 1000, self.reserve);
+
+        let mut ticker = tokio::time::interval(Duration::from_secs(self.interval));
+        // The first tick completes immediately, nothing has been requested yet
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            self.run_once().await;
+        }
+    }
+
+    async fn run_once(&self) {
+        let popular = self.get_popular(self.top);
+        self.decay();
+
+        let now = time::get_timestamp_millis();
+        let mut decisions = Vec::new();
+        for (provider, username, requests) in popular {
+            // Users never fetched are left to foreground requests
+            let expiration = match self.users.repository.get_by_username(&provider, &username).await {
+                Ok(Some(user)) => user.expiration,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::error!("Failed to get {} user {}: {:?}", provider, username, e);
+                    continue;
+                }
+            };
+            if expiration - now > self.ahead {
+                continue;
+            }
+
+            let remaining = self.users.get_remaining(&provider);
+            let action = if remaining.is_some_and(|remaining| remaining <= self.reserve) {
+                log::info!("Refresh skipped for {} user {}, {} requests remaining", provider, username, remaining.unwrap_or_default());
+                "skipped"
+            } else {
+                match self.users.refresh(&provider, &username).await {
+                    Ok(Some(user)) => {
+                        // The cached avatar expires along with the profile, so refresh it too
+                        if let Err(e) = self.users.refresh_avatar(&user).await {
+                            log::warn!("Failed to refresh the avatar of {} user {}: {}", provider, username, e);
+                        }
+                        log::info!("Refreshed {} user {} with {} requests", provider, username, requests);
+                        "refreshed"
+                    },
+                    Ok(None) => {
+                        log::info!("Refreshed {} user {}, who no longer exists", provider, username);
+                        "refreshed"
+                    },
+                    Err(e) => {
+                        log::warn!("Failed to refresh {} user {}: {}", provider, username, e);
+                        "failed"
+                    }
+                }
+            };
+            decisions.push(RefreshDecision {
+                provider,
+                username,
+                requests,
+                expiration,
+                remaining,
+                action: action.to_string(),
+            });
+        }
+
+        let mut status = self.status.lock().unwrap();
+        let count = |action: &str| decisions.iter().filter(|decision| decision.action == action).count() as u64;
+        let (refreshed, skipped, failed) = (count("refreshed"), count("skipped"), count("failed"));
+        if !decisions.is_empty() {
+            log::info!("Refresh run: {} refreshed, {} skipped, {} failed", refreshed, skipped, failed);
+        }
+        status.last_run = Some(time::get_timestamp());
+        status.runs += 1;
+        status.refreshed += refreshed;
+        status.skipped += skipped;
+        status.failed += failed;
+        status.last_decisions = decisions;
+    }
+
+    // Get the most requested users, most requested first
+    fn get_popular(&self, top: usize) -> Vec<(String, String, u64)> {
+        let counts = self.counts.lock().unwrap();
+        let mut popular: Vec<(String, String, u64)> = counts.iter()
+            .map(|((provider, username), requests)| (provider.clone(), username.clone(), *requests))
+            .collect();
+        popular.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.cmp(&b.1)));
+        popular.truncate(top);
+        popular
+    }
+
+    fn decay(&self) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.values_mut().for_each(|requests| *requests /= 2);
+        counts.retain(|_, requests| *requests > 0);
+    }
+}
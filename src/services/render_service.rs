@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use tokio::sync::Semaphore;
+
+
+#[derive(Debug)]
+pub enum RenderError {
+    // The render queue is at capacity, the caller should retry later
+    QueueFull,
+    // The render job panicked or failed to produce an image
+    Failed(String),
+}
+
+pub struct RenderService {
+    pub permits: Arc<Semaphore>,
+    pub pending: Arc<AtomicUsize>,
+    pub workers: usize,
+    pub queue_depth: usize,
+}
+
+// Decrements the pending counter once a job leaves the pool, even if the request was dropped.
+struct PendingGuard(Arc<AtomicUsize>);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RenderService {
+    pub fn new(workers: usize, queue_depth: usize) -> Self {
+        let workers = workers.max(1);
+        RenderService {
+            permits: Arc::new(Semaphore::new(workers)),
+            pending: Arc::new(AtomicUsize::new(0)),
+            workers,
+            queue_depth,
+        }
+    }
+
+    // Number of jobs waiting for a render worker.
+    pub fn queue_length(&self) -> usize {
+        self.pending.load(Ordering::SeqCst).saturating_sub(self.workers)
+    }
+
+    // Run a CPU bound job on the blocking pool, bounded by the number of workers and queue depth.
+    pub async fn render<F, T>(&self, job: F) -> Result<T, RenderError>
+    where
+        F: FnOnce() -> Result<T, Box<dyn std::error::Error + Send + Sync>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let pending = self.pending.fetch_add(1, Ordering::SeqCst) + 1;
+        let guard = PendingGuard(self.pending.clone());
+        if pending > self.workers + self.queue_depth {
+            log::warn!("Render queue is full, pending: {}!", pending - 1);
+            return Err(RenderError::QueueFull);
+        }
+
+        let queued_at = Instant::now();
+        let permit = self.permits.clone().acquire_owned().await
+            .map_err(|e| RenderError::Failed(e.to_string()))?;
+        let waited = queued_at.elapsed();
+
+        let started_at = Instant::now();
+        // The job holds the permit and stays pending until it is done, even if the request is dropped while it runs
+        let result = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let _guard = guard;
+            job()
+        }).await;
+        let rendered = started_at.elapsed();
+        log::debug!("Rendered in {}ms, waited {}ms, queue length: {}",
+            rendered.as_millis(),
+            waited.as_millis(),
+            self.queue_length()
+        );
+
+        match result {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) => Err(RenderError::Failed(e.to_string())),
+            Err(e) => Err(RenderError::Failed(e.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn dropped_render_keeps_its_worker_until_it_finishes() {
+        let service = Arc::new(RenderService::new(1, 0));
+        let render_service = service.clone();
+        let request = tokio::spawn(async move {
+            render_service.render(|| {
+                std::thread::sleep(Duration::from_millis(300));
+                Ok(())
+            }).await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        request.abort();
+        let _ = request.await;
+
+        assert_eq!(service.permits.available_permits(), 0);
+        assert!(matches!(service.render(|| Ok(())).await, Err(RenderError::QueueFull)));
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(service.permits.available_permits(), 1);
+        assert!(service.render(|| Ok(())).await.is_ok());
+    }
+}
@@ -1,9 +1,9 @@
 
 pub fn is_str_valid_length(value: &str, min: usize, max: usize) -> bool {
-    if !(value.len() >= min) {
+    if value.len() < min {
         return false;
     }
-    if !(value.len() <= max) {
+    if value.len() > max {
         return false;
     }
     true
@@ -21,4 +21,8 @@ pub fn is_str_alphanumeric(value: &str) -> bool {
 
 pub fn is_str_delimiter_free(value: &str) -> bool {
     is_str_valid_pattern(value, ":/?#[]@!$&'()*+,;=")
+}
+
+pub fn is_str_slug(value: &str) -> bool {
+    value.chars().all(|char| char.is_ascii_lowercase() || char.is_ascii_digit() || char == '-' || char == '_')
 }
\ No newline at end of file